@@ -0,0 +1,79 @@
+//! Short-lived cache of A2S query results.
+
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use parking_lot::Mutex;
+
+use super::{query_info, query_players, query_rules};
+
+/// Kind of A2S query
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum QueryKind {
+    Info,
+    Players,
+    Rules,
+}
+
+/// Cached query result (JSON body or error message)
+struct CacheEntry {
+    fetched_at: Instant,
+    result: Result<String, String>,
+}
+
+/// Cache of A2S results serialized as JSON.
+///
+/// At most one query per target and kind is sent within the TTL no matter how
+/// many HTTP requests arrive, so the gateway cannot be used to flood the game
+/// server. Concurrent requests wait for the in-flight query instead of starting
+/// their own; queries for other targets or kinds are not held up by it.
+pub struct A2sCache {
+    ttl: Duration,
+    entries: Mutex<HashMap<(SocketAddr, QueryKind), Slot>>,
+}
+
+/// Result of one target and kind, locked while it is being queried
+type Slot = Arc<tokio::sync::Mutex<Option<CacheEntry>>>;
+
+impl A2sCache {
+    /// Create a cache keeping results for `ttl`.
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            entries: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Get a query result as JSON, querying the server if the cached one expired.
+    pub async fn get(&self, addr: SocketAddr, kind: QueryKind) -> Result<String, String> {
+        let slot = self.entries.lock().entry((addr, kind)).or_default().clone();
+        // Held across the query so concurrent requests share one result
+        let mut entry = slot.lock().await;
+
+        if let Some(entry) = entry.as_ref() {
+            if entry.fetched_at.elapsed() < self.ttl {
+                return entry.result.clone();
+            }
+        }
+
+        let result = match kind {
+            QueryKind::Info => query_info(addr).await.map(|info| to_json(&info)),
+            QueryKind::Players => query_players(addr).await.map(|players| to_json(&players)),
+            QueryKind::Rules => query_rules(addr).await.map(|rules| to_json(&rules)),
+        }
+        .map_err(|e| e.to_string());
+
+        *entry = Some(CacheEntry {
+            fetched_at: Instant::now(),
+            result: result.clone(),
+        });
+
+        result
+    }
+}
+
+fn to_json<T: serde::Serialize>(value: &T) -> String {
+    serde_json::to_string(value).unwrap_or_default()
+}
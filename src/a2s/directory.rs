@@ -0,0 +1,136 @@
+//! Directory of game servers kept up to date with periodic A2S queries.
+
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use parking_lot::RwLock;
+use serde::Serialize;
+
+use super::{query_info, query_players, A2sError, A2sInfo, A2sPlayer};
+use crate::config::DirectoryServer;
+
+/// Interval between polls of the configured servers
+const POLL_INTERVAL: Duration = Duration::from_secs(15);
+
+/// Last known state of a listed server
+#[derive(Debug, Clone, Serialize)]
+pub struct ServerStatus {
+    /// Game server address (`host:port`)
+    pub address: String,
+    /// WebSocket signaling URL of the server's webxash plugin
+    pub signaling_url: String,
+    /// Whether the last poll got an answer
+    pub online: bool,
+    /// Server information from the last successful poll
+    pub info: Option<A2sInfo>,
+    /// Player list from the last successful poll
+    pub players: Vec<A2sPlayer>,
+    /// Unix time of the last successful poll
+    pub last_seen: Option<u64>,
+    /// Error of the last poll, if it failed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Server list response body
+#[derive(Serialize)]
+struct DirectoryResponse<'a> {
+    servers: &'a [ServerStatus],
+}
+
+/// Configured server list with the results of the latest poll.
+pub struct ServerDirectory {
+    servers: Vec<DirectoryServer>,
+    statuses: RwLock<Vec<ServerStatus>>,
+}
+
+impl ServerDirectory {
+    /// Create a directory for `servers`; signaling URLs default to
+    /// `ws://<address>/ws`, since other servers need not share our base path.
+    pub fn new(servers: Vec<DirectoryServer>) -> Self {
+        let statuses = servers
+            .iter()
+            .map(|server| ServerStatus {
+                address: server.address.clone(),
+                signaling_url: server
+                    .signaling_url
+                    .clone()
+                    .unwrap_or_else(|| format!("ws://{}/ws", server.address)),
+                online: false,
+                info: None,
+                players: Vec::new(),
+                last_seen: None,
+                error: None,
+            })
+            .collect();
+
+        Self {
+            servers,
+            statuses: RwLock::new(statuses),
+        }
+    }
+
+    /// Whether any servers are configured.
+    pub fn is_empty(&self) -> bool {
+        self.servers.is_empty()
+    }
+
+    /// Poll all servers forever.
+    pub async fn run(&self) {
+        let mut interval = tokio::time::interval(POLL_INTERVAL);
+        loop {
+            interval.tick().await;
+            self.poll().await;
+        }
+    }
+
+    /// Query all servers concurrently and update their statuses.
+    async fn poll(&self) {
+        let results =
+            futures::future::join_all(self.servers.iter().map(|s| query_server(&s.address))).await;
+
+        let now = unix_time();
+        let mut statuses = self.statuses.write();
+        for (status, result) in statuses.iter_mut().zip(results) {
+            match result {
+                Ok((info, players)) => {
+                    status.online = true;
+                    status.info = Some(info);
+                    status.players = players;
+                    status.last_seen = Some(now);
+                    status.error = None;
+                }
+                Err(e) => {
+                    // Keep the last known info so the portal can show when it was seen
+                    status.online = false;
+                    status.error = Some(e.to_string());
+                }
+            }
+        }
+    }
+
+    /// Serialize the current statuses as JSON.
+    pub fn to_json(&self) -> String {
+        let statuses = self.statuses.read();
+        serde_json::to_string(&DirectoryResponse { servers: &statuses }).unwrap_or_default()
+    }
+}
+
+/// Resolve a server address and query its info and players.
+async fn query_server(address: &str) -> Result<(A2sInfo, Vec<A2sPlayer>), A2sError> {
+    let addr = tokio::net::lookup_host(address)
+        .await?
+        .next()
+        .ok_or("address did not resolve")?;
+
+    let info = query_info(addr).await?;
+    // Player lists are optional; some servers disable them
+    let players = query_players(addr).await.unwrap_or_default();
+
+    Ok((info, players))
+}
+
+fn unix_time() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs())
+}
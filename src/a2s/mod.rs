@@ -0,0 +1,391 @@
+//! A2S server query client (GoldSrc/Source query protocol).
+//!
+//! Browsers cannot send UDP, so the HTTP server runs A2S queries on their
+//! behalf. See <https://developer.valvesoftware.com/wiki/Server_queries>.
+
+mod cache;
+mod directory;
+
+use std::net::SocketAddr;
+use std::time::Duration;
+
+use serde::Serialize;
+use tokio::net::UdpSocket;
+
+pub use cache::{A2sCache, QueryKind};
+pub use directory::ServerDirectory;
+
+/// Time to wait for each response packet
+const RESPONSE_TIMEOUT: Duration = Duration::from_millis(1500);
+
+/// Maximum UDP payload we expect from the game server
+const MAX_PACKET_SIZE: usize = 4096;
+
+/// Challenge requests answered before giving up
+const MAX_CHALLENGE_ROUNDS: usize = 3;
+
+/// Single-packet response header
+const HEADER_SINGLE: i32 = -1;
+/// Split-packet response header
+const HEADER_SPLIT: i32 = -2;
+
+/// Request and response type bytes
+const A2S_INFO: u8 = b'T';
+const A2S_PLAYER: u8 = b'U';
+const A2S_RULES: u8 = b'V';
+const S2C_CHALLENGE: u8 = b'A';
+const S2A_INFO_SOURCE: u8 = b'I';
+const S2A_INFO_GOLDSRC: u8 = b'm';
+const S2A_PLAYER: u8 = b'D';
+const S2A_RULES: u8 = b'E';
+
+/// `A2S_INFO` payload
+const INFO_PAYLOAD: &[u8] = b"Source Engine Query\0";
+
+/// Query error
+pub type A2sError = Box<dyn std::error::Error + Send + Sync>;
+
+/// Server information (`A2S_INFO` response)
+#[derive(Debug, Clone, Serialize)]
+pub struct A2sInfo {
+    pub protocol: u8,
+    pub name: String,
+    pub map: String,
+    pub folder: String,
+    pub game: String,
+    pub players: u8,
+    pub max_players: u8,
+    pub bots: u8,
+    /// `d` dedicated, `l` listen, `p` SourceTV/HLTV
+    pub server_type: String,
+    /// `l` Linux, `w` Windows, `m` macOS
+    pub environment: String,
+    pub password: bool,
+    pub vac: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub port: Option<u16>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub keywords: Option<String>,
+}
+
+/// A connected player (`A2S_PLAYER` response entry)
+#[derive(Debug, Clone, Serialize)]
+pub struct A2sPlayer {
+    pub name: String,
+    pub score: i32,
+    /// Seconds connected
+    pub duration: f32,
+}
+
+/// A server rule (`A2S_RULES` response entry)
+#[derive(Debug, Clone, Serialize)]
+pub struct A2sRule {
+    pub name: String,
+    pub value: String,
+}
+
+/// Query server information.
+pub async fn query_info(addr: SocketAddr) -> Result<A2sInfo, A2sError> {
+    let mut request = vec![0xFF, 0xFF, 0xFF, 0xFF, A2S_INFO];
+    request.extend_from_slice(INFO_PAYLOAD);
+
+    let response = query(addr, &request, None).await?;
+    let mut reader = Reader::new(&response);
+
+    match reader.u8()? {
+        S2A_INFO_SOURCE => parse_info_source(&mut reader),
+        S2A_INFO_GOLDSRC => parse_info_goldsrc(&mut reader),
+        other => Err(format!("unexpected A2S_INFO response type 0x{other:02x}").into()),
+    }
+}
+
+/// Query the player list.
+pub async fn query_players(addr: SocketAddr) -> Result<Vec<A2sPlayer>, A2sError> {
+    let request = [0xFF, 0xFF, 0xFF, 0xFF, A2S_PLAYER];
+    let response = query(addr, &request, Some([0xFF; 4])).await?;
+    let mut reader = Reader::new(&response);
+
+    expect_type(&mut reader, S2A_PLAYER)?;
+    let count = reader.u8()?;
+    let mut players = Vec::with_capacity(usize::from(count));
+    for _ in 0..count {
+        let _index = reader.u8()?;
+        players.push(A2sPlayer {
+            name: reader.string()?,
+            score: reader.i32()?,
+            duration: reader.f32()?,
+        });
+    }
+
+    Ok(players)
+}
+
+/// Query the server rules (cvars).
+pub async fn query_rules(addr: SocketAddr) -> Result<Vec<A2sRule>, A2sError> {
+    let request = [0xFF, 0xFF, 0xFF, 0xFF, A2S_RULES];
+    let response = query(addr, &request, Some([0xFF; 4])).await?;
+    let mut reader = Reader::new(&response);
+
+    expect_type(&mut reader, S2A_RULES)?;
+    let count = reader.u16()?;
+    let mut rules = Vec::with_capacity(usize::from(count));
+    for _ in 0..count {
+        // Some servers report more rules than they send
+        let Ok(name) = reader.string() else {
+            break;
+        };
+        rules.push(A2sRule {
+            name,
+            value: reader.string()?,
+        });
+    }
+
+    Ok(rules)
+}
+
+/// Send a request, answering challenges, and return the reassembled response payload
+/// (starting at the response type byte).
+///
+/// `challenge` is the placeholder appended to the first request (`A2S_PLAYER`/`A2S_RULES`);
+/// `A2S_INFO` only appends a challenge when the server asks for one.
+async fn query(
+    addr: SocketAddr,
+    request: &[u8],
+    challenge: Option<[u8; 4]>,
+) -> Result<Vec<u8>, A2sError> {
+    let bind_addr = if addr.is_ipv4() {
+        "0.0.0.0:0"
+    } else {
+        "[::]:0"
+    };
+    let socket = UdpSocket::bind(bind_addr).await?;
+    socket.connect(addr).await?;
+
+    let mut packet = request.to_vec();
+    if let Some(challenge) = challenge {
+        packet.extend_from_slice(&challenge);
+    }
+
+    for _ in 0..MAX_CHALLENGE_ROUNDS {
+        socket.send(&packet).await?;
+        let response = receive(&socket).await?;
+
+        if response.first() == Some(&S2C_CHALLENGE) && response.len() >= 5 {
+            packet = request.to_vec();
+            packet.extend_from_slice(&response[1..5]);
+            continue;
+        }
+
+        return Ok(response);
+    }
+
+    Err("server kept answering with challenges".into())
+}
+
+/// Receive a response, reassembling split packets.
+async fn receive(socket: &UdpSocket) -> Result<Vec<u8>, A2sError> {
+    let mut buf = vec![0u8; MAX_PACKET_SIZE];
+    let mut parts: Vec<Option<Vec<u8>>> = Vec::new();
+
+    loop {
+        let n = tokio::time::timeout(RESPONSE_TIMEOUT, socket.recv(&mut buf))
+            .await
+            .map_err(|_| "query timed out")??;
+        let mut reader = Reader::new(&buf[..n]);
+
+        match reader.i32()? {
+            HEADER_SINGLE => return Ok(reader.rest().to_vec()),
+            HEADER_SPLIT => {
+                // GoldSrc split header: id (4), packet number (upper nibble) / total (lower nibble)
+                let _id = reader.i32()?;
+                let numbering = reader.u8()?;
+                let (number, total) = (usize::from(numbering >> 4), usize::from(numbering & 0x0F));
+                if total == 0 || number >= total {
+                    return Err("invalid split packet numbering".into());
+                }
+
+                if parts.len() != total {
+                    parts = vec![None; total];
+                }
+                parts[number] = Some(reader.rest().to_vec());
+
+                if parts.iter().all(Option::is_some) {
+                    let payload: Vec<u8> = parts.into_iter().flatten().flatten().collect();
+                    let mut reader = Reader::new(&payload);
+                    if reader.i32()? != HEADER_SINGLE {
+                        return Err("invalid reassembled packet header".into());
+                    }
+                    return Ok(reader.rest().to_vec());
+                }
+            }
+            _ => return Err("invalid response header".into()),
+        }
+    }
+}
+
+fn expect_type(reader: &mut Reader<'_>, expected: u8) -> Result<(), A2sError> {
+    let actual = reader.u8()?;
+    if actual == expected {
+        Ok(())
+    } else {
+        Err(format!("unexpected response type 0x{actual:02x}").into())
+    }
+}
+
+/// Parse a Source-format `A2S_INFO` response (after the type byte).
+fn parse_info_source(reader: &mut Reader<'_>) -> Result<A2sInfo, A2sError> {
+    let protocol = reader.u8()?;
+    let name = reader.string()?;
+    let map = reader.string()?;
+    let folder = reader.string()?;
+    let game = reader.string()?;
+    let _app_id = reader.u16()?;
+
+    let mut info = A2sInfo {
+        protocol,
+        name,
+        map,
+        folder,
+        game,
+        players: reader.u8()?,
+        max_players: reader.u8()?,
+        bots: reader.u8()?,
+        server_type: char::from(reader.u8()?).to_string(),
+        environment: char::from(reader.u8()?).to_string(),
+        password: reader.u8()? != 0,
+        vac: reader.u8()? != 0,
+        version: None,
+        port: None,
+        keywords: None,
+    };
+
+    // Version and extra data flag are optional on old servers
+    let Ok(version) = reader.string() else {
+        return Ok(info);
+    };
+    info.version = Some(version);
+
+    if let Ok(edf) = reader.u8() {
+        if edf & 0x80 != 0 {
+            info.port = Some(reader.u16()?);
+        }
+        if edf & 0x10 != 0 {
+            let _steam_id = reader.u64()?;
+        }
+        if edf & 0x40 != 0 {
+            let _tv_port = reader.u16()?;
+            let _tv_name = reader.string()?;
+        }
+        if edf & 0x20 != 0 {
+            info.keywords = Some(reader.string()?);
+        }
+    }
+
+    Ok(info)
+}
+
+/// Parse an obsolete GoldSrc-format `A2S_INFO` response (after the type byte).
+fn parse_info_goldsrc(reader: &mut Reader<'_>) -> Result<A2sInfo, A2sError> {
+    let _address = reader.string()?;
+    let name = reader.string()?;
+    let map = reader.string()?;
+    let folder = reader.string()?;
+    let game = reader.string()?;
+    let players = reader.u8()?;
+    let max_players = reader.u8()?;
+    let protocol = reader.u8()?;
+    let server_type = char::from(reader.u8()?).to_ascii_lowercase().to_string();
+    let environment = char::from(reader.u8()?).to_ascii_lowercase().to_string();
+    let password = reader.u8()? != 0;
+
+    // Half-Life mod information
+    if reader.u8()? != 0 {
+        let _link = reader.string()?;
+        let _download_link = reader.string()?;
+        let _null = reader.u8()?;
+        let _version = reader.i32()?;
+        let _size = reader.i32()?;
+        let _mod_type = reader.u8()?;
+        let _dll = reader.u8()?;
+    }
+
+    Ok(A2sInfo {
+        protocol,
+        name,
+        map,
+        folder,
+        game,
+        players,
+        max_players,
+        server_type,
+        environment,
+        password,
+        vac: reader.u8()? != 0,
+        bots: reader.u8()?,
+        version: None,
+        port: None,
+        keywords: None,
+    })
+}
+
+/// Little-endian reader over a response payload.
+struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Self { data, pos: 0 }
+    }
+
+    fn take<const N: usize>(&mut self) -> Result<[u8; N], A2sError> {
+        let bytes = self
+            .data
+            .get(self.pos..self.pos + N)
+            .ok_or("truncated response")?;
+        self.pos += N;
+
+        let mut out = [0u8; N];
+        out.copy_from_slice(bytes);
+        Ok(out)
+    }
+
+    fn u8(&mut self) -> Result<u8, A2sError> {
+        Ok(self.take::<1>()?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16, A2sError> {
+        Ok(u16::from_le_bytes(self.take()?))
+    }
+
+    fn i32(&mut self) -> Result<i32, A2sError> {
+        Ok(i32::from_le_bytes(self.take()?))
+    }
+
+    fn u64(&mut self) -> Result<u64, A2sError> {
+        Ok(u64::from_le_bytes(self.take()?))
+    }
+
+    fn f32(&mut self) -> Result<f32, A2sError> {
+        Ok(f32::from_le_bytes(self.take()?))
+    }
+
+    /// Read a null-terminated string (lossy UTF-8).
+    fn string(&mut self) -> Result<String, A2sError> {
+        let rest = &self.data[self.pos.min(self.data.len())..];
+        let len = rest
+            .iter()
+            .position(|&b| b == 0)
+            .ok_or("unterminated string")?;
+        self.pos += len + 1;
+
+        Ok(String::from_utf8_lossy(&rest[..len]).into_owned())
+    }
+
+    fn rest(&self) -> &'a [u8] {
+        &self.data[self.pos.min(self.data.len())..]
+    }
+}
@@ -1,40 +1,87 @@
-//! UDP bridge between WebRTC data channels and game server.
+//! UDP bridge between browser transports and game server.
 //!
 //! Adapted from the webxash3d-proxy bridge module.
 
+mod stats;
+mod transport;
+
+use std::collections::HashMap;
+use std::net::SocketAddr;
 use std::sync::Arc;
 
+use once_cell::sync::Lazy;
+use parking_lot::Mutex;
 use tokio::net::UdpSocket;
 use tokio::sync::Notify;
-use webrtc::data_channel::data_channel_message::DataChannelMessage;
-use webrtc::data_channel::RTCDataChannel;
+
+use crate::metrics;
+
+pub use stats::{BridgeStats, BridgeStatsSnapshot};
+pub use transport::{
+    DataChannelTransport, DatagramTransport, Transport, WebSocketTransport, DATAGRAM_BUFFER_SIZE,
+};
 
 /// Maximum packet size for GoldSrc protocol
 const MAX_PACKET_SIZE: usize = 65536;
 
-/// Bridge between WebRTC data channels and UDP socket to game server.
+/// Data queued on the transport above which server packets are dropped.
 ///
-/// Uses two channels to match the original client expectations:
-/// - `write` channel: proxy sends TO browser (server -> client)
-/// - `read` channel: proxy receives FROM browser (client -> server)
+/// Game packets are useless once stale, so a browser that cannot keep up loses
+/// packets instead of building up latency.
+const MAX_BUFFERED_AMOUNT: usize = 1024 * 1024;
+
+/// Local UDP addresses of active bridges, mapped to their statistics.
+///
+/// The game server sees web clients as connecting from these addresses.
+static ACTIVE_BRIDGES: Lazy<Mutex<HashMap<SocketAddr, Arc<BridgeStats>>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// Check whether a game client address (as reported by the engine) belongs to a bridge.
+pub fn is_bridge_addr(addr: &str) -> bool {
+    addr.parse::<SocketAddr>()
+        .is_ok_and(|addr| ACTIVE_BRIDGES.lock().contains_key(&addr))
+}
+
+/// Get statistics of all active bridges.
+pub fn all_stats() -> Vec<BridgeStatsSnapshot> {
+    let mut stats: Vec<_> = ACTIVE_BRIDGES
+        .lock()
+        .values()
+        .map(|stats| stats.snapshot())
+        .collect();
+    stats.sort_by_key(|stats| stats.created_at);
+    stats
+}
+
+/// Get statistics of the bridge serving a client.
+pub fn stats_for(client_id: &str) -> Option<BridgeStatsSnapshot> {
+    ACTIVE_BRIDGES
+        .lock()
+        .values()
+        .find(|stats| stats.client_id() == client_id)
+        .map(|stats| stats.snapshot())
+}
+
+/// Bridge between a browser transport and UDP socket to game server.
 pub struct Bridge {
-    /// Channel for sending data TO the browser (server responses)
-    write_channel: Arc<RTCDataChannel>,
-    /// Channel for receiving data FROM the browser (client commands)
-    read_channel: Arc<RTCDataChannel>,
+    /// Packets to and from the browser
+    transport: Arc<dyn Transport>,
     /// UDP socket connected to game server
     udp_socket: Arc<UdpSocket>,
     /// Shutdown signal
     shutdown: Arc<Notify>,
     /// Client identifier for logging
     client_id: String,
+    /// Local address of the UDP socket (the client address seen by the game server)
+    local_addr: SocketAddr,
+    /// Traffic statistics
+    stats: Arc<BridgeStats>,
 }
 
 impl Bridge {
-    /// Create a new bridge connecting data channels to a game server.
+    /// Create a new bridge connecting a browser transport to a game server.
     pub async fn new(
-        write_channel: Arc<RTCDataChannel>,
-        read_channel: Arc<RTCDataChannel>,
+        transport: Arc<dyn Transport>,
         server_addr: &str,
         client_id: String,
     ) -> Result<Self, Box<dyn std::error::Error + Send + Sync>> {
@@ -46,42 +93,54 @@ impl Bridge {
 
         let local_addr = udp_socket.local_addr()?;
         println!(
-            "[WEBXASH] Bridge {}: UDP socket {} -> {}",
-            client_id, local_addr, server_addr
+            "[WEBXASH] Bridge {}: UDP socket {} -> {} over {}",
+            client_id,
+            local_addr,
+            server_addr,
+            transport.name()
         );
 
+        let stats = Arc::new(BridgeStats::new(
+            client_id.clone(),
+            local_addr,
+            transport.name(),
+        ));
+        ACTIVE_BRIDGES.lock().insert(local_addr, stats.clone());
+        metrics::BRIDGES.inc();
+
         Ok(Self {
-            write_channel,
-            read_channel,
+            transport,
             udp_socket: Arc::new(udp_socket),
             shutdown: Arc::new(Notify::new()),
             client_id,
+            local_addr,
+            stats,
         })
     }
 
     /// Start bidirectional forwarding.
     pub async fn start(self: Arc<Self>) {
-        // Spawn UDP -> WebRTC forwarder (server responses to browser via write channel)
-        let udp_to_webrtc = tokio::spawn({
+        // Spawn UDP -> browser forwarder (server responses)
+        let udp_to_browser = tokio::spawn({
             let bridge = self.clone();
             async move {
-                bridge.forward_udp_to_webrtc().await;
+                bridge.forward_udp_to_browser().await;
             }
         });
 
-        // Setup WebRTC -> UDP forwarder (browser commands to server via read channel)
-        self.setup_webrtc_to_udp();
+        // Setup browser -> UDP forwarder (client commands)
+        self.setup_browser_to_udp();
 
         // Wait for shutdown signal
         self.shutdown.notified().await;
 
         // Cleanup
-        udp_to_webrtc.abort();
+        udp_to_browser.abort();
         println!("[WEBXASH] Bridge {} shut down", self.client_id);
     }
 
-    /// Forward packets from UDP (game server) to WebRTC write channel (browser).
-    async fn forward_udp_to_webrtc(&self) {
+    /// Forward packets from UDP (game server) to the transport (browser).
+    async fn forward_udp_to_browser(&self) {
         let mut buf = vec![0u8; MAX_PACKET_SIZE];
 
         loop {
@@ -89,15 +148,27 @@ impl Bridge {
                 result = self.udp_socket.recv(&mut buf) => {
                     match result {
                         Ok(n) if n > 0 => {
+                            if self.transport.buffered_amount().await > MAX_BUFFERED_AMOUNT
+                                || self.transport.max_packet_size().is_some_and(|max| n > max)
+                            {
+                                self.stats.record_dropped();
+                                continue;
+                            }
+
                             let data = bytes::Bytes::copy_from_slice(&buf[..n]);
 
-                            if let Err(e) = self.write_channel.send(&data).await {
+                            if let Err(e) = self.transport.send(&data).await {
+                                self.stats.record_send_failure_to_client();
                                 eprintln!(
-                                    "[WEBXASH] Bridge {}: Failed to send to write channel: {}",
-                                    self.client_id, e
+                                    "[WEBXASH] Bridge {}: Failed to send to {}: {}",
+                                    self.client_id,
+                                    self.transport.name(),
+                                    e
                                 );
                                 break;
                             }
+
+                            self.stats.record_to_client(n);
                         }
                         Ok(_) => {
                             // Empty packet, continue
@@ -118,46 +189,36 @@ impl Bridge {
         }
     }
 
-    /// Setup callback for WebRTC read channel -> UDP forwarding (browser to game server).
-    fn setup_webrtc_to_udp(&self) {
+    /// Setup transport callbacks for browser -> UDP forwarding.
+    fn setup_browser_to_udp(&self) {
         let udp_socket = self.udp_socket.clone();
         let client_id = self.client_id.clone();
-        let shutdown = self.shutdown.clone();
-
-        // Handle incoming messages on the read channel
-        self.read_channel
-            .on_message(Box::new(move |msg: DataChannelMessage| {
-                let udp_socket = udp_socket.clone();
-                let client_id = client_id.clone();
-
-                Box::pin(async move {
-                    let data = msg.data;
-
-                    if let Err(e) = udp_socket.send(&data).await {
-                        eprintln!(
-                            "[WEBXASH] Bridge {}: Failed to send to UDP: {}",
-                            client_id, e
-                        );
+        let stats = self.stats.clone();
+
+        // Handle incoming packets from the browser
+        self.transport.on_packet(Arc::new(move |data| {
+            let udp_socket = udp_socket.clone();
+            let client_id = client_id.clone();
+            let stats = stats.clone();
+
+            Box::pin(async move {
+                match udp_socket.send(&data).await {
+                    Ok(n) => stats.record_to_game(n),
+                    Err(e) => {
+                        stats.record_send_failure_to_game();
+                        eprintln!("[WEBXASH] Bridge {client_id}: Failed to send to UDP: {e}");
                     }
-                })
-            }));
-
-        // Handle read channel close
-        let shutdown_clone = shutdown.clone();
-        let client_id = self.client_id.clone();
-        self.read_channel.on_close(Box::new(move || {
-            println!("[WEBXASH] Bridge {}: Read channel closed", client_id);
-            shutdown_clone.notify_one();
-            Box::pin(async {})
+                }
+            })
         }));
 
-        // Handle read channel errors
-        let shutdown_clone = shutdown;
+        // Handle transport close
+        let shutdown = self.shutdown.clone();
         let client_id = self.client_id.clone();
-        self.read_channel.on_error(Box::new(move |e| {
-            eprintln!("[WEBXASH] Bridge {}: Read channel error: {}", client_id, e);
-            shutdown_clone.notify_one();
-            Box::pin(async {})
+        let transport = self.transport.name();
+        self.transport.on_close(Box::new(move || {
+            println!("[WEBXASH] Bridge {client_id}: {transport} transport closed");
+            shutdown.notify_one();
         }));
     }
 
@@ -169,6 +230,8 @@ impl Bridge {
 
 impl Drop for Bridge {
     fn drop(&mut self) {
+        ACTIVE_BRIDGES.lock().remove(&self.local_addr);
+        metrics::BRIDGES.dec();
         println!("[WEBXASH] Bridge {} dropped", self.client_id);
     }
 }
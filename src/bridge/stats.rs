@@ -0,0 +1,177 @@
+//! Per-bridge traffic statistics.
+//!
+//! Counters are updated by the forwarding tasks and read as snapshots by the
+//! console command, HTTP endpoints and metrics.
+
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use parking_lot::Mutex;
+use serde::Serialize;
+
+use crate::metrics;
+
+/// Live counters of one bridge
+pub struct BridgeStats {
+    client_id: String,
+    local_addr: SocketAddr,
+    transport: &'static str,
+    /// Unix time (ms) the bridge was created
+    created_at: u64,
+    packets_to_game: AtomicU64,
+    bytes_to_game: AtomicU64,
+    packets_to_client: AtomicU64,
+    bytes_to_client: AtomicU64,
+    send_failures_to_game: AtomicU64,
+    send_failures_to_client: AtomicU64,
+    dropped_packets: AtomicU64,
+    /// Unix time (ms) of the last packet to the game server, 0 if none
+    last_to_game: AtomicU64,
+    /// Unix time (ms) of the last packet to the browser, 0 if none
+    last_to_client: AtomicU64,
+    rate: Mutex<RateWindow>,
+}
+
+/// Packets counted in the current one-second window
+#[derive(Default)]
+struct RateWindow {
+    second: u64,
+    count: u64,
+    peak: u64,
+}
+
+/// Point-in-time copy of a bridge's statistics
+#[derive(Debug, Clone, Serialize)]
+pub struct BridgeStatsSnapshot {
+    pub client_id: String,
+    /// Local UDP address (the client address seen by the game server)
+    pub local_addr: SocketAddr,
+    /// Browser transport (`webrtc` or `websocket`)
+    pub transport: &'static str,
+    /// Unix time (ms) the bridge was created
+    pub created_at: u64,
+    pub packets_to_game: u64,
+    pub bytes_to_game: u64,
+    pub packets_to_client: u64,
+    pub bytes_to_client: u64,
+    pub send_failures_to_game: u64,
+    pub send_failures_to_client: u64,
+    /// Packets discarded without sending (browser not keeping up)
+    pub dropped_packets: u64,
+    /// Unix time (ms) of the last packet to the game server
+    pub last_to_game: Option<u64>,
+    /// Unix time (ms) of the last packet to the browser
+    pub last_to_client: Option<u64>,
+    /// Highest packets per second seen, both directions combined
+    pub peak_packet_rate: u64,
+}
+
+impl BridgeStats {
+    pub fn new(client_id: String, local_addr: SocketAddr, transport: &'static str) -> Self {
+        Self {
+            client_id,
+            local_addr,
+            transport,
+            created_at: unix_millis(),
+            packets_to_game: AtomicU64::new(0),
+            bytes_to_game: AtomicU64::new(0),
+            packets_to_client: AtomicU64::new(0),
+            bytes_to_client: AtomicU64::new(0),
+            send_failures_to_game: AtomicU64::new(0),
+            send_failures_to_client: AtomicU64::new(0),
+            dropped_packets: AtomicU64::new(0),
+            last_to_game: AtomicU64::new(0),
+            last_to_client: AtomicU64::new(0),
+            rate: Mutex::new(RateWindow::default()),
+        }
+    }
+
+    pub fn client_id(&self) -> &str {
+        &self.client_id
+    }
+
+    /// Count a packet sent to the game server.
+    pub fn record_to_game(&self, bytes: usize) {
+        let bytes = bytes as u64;
+        self.packets_to_game.fetch_add(1, Ordering::Relaxed);
+        self.bytes_to_game.fetch_add(bytes, Ordering::Relaxed);
+        self.last_to_game
+            .store(self.count_packet(), Ordering::Relaxed);
+        metrics::PACKETS_TO_GAME.inc();
+        metrics::BYTES_TO_GAME.add(bytes);
+    }
+
+    /// Count a packet sent to the browser.
+    pub fn record_to_client(&self, bytes: usize) {
+        let bytes = bytes as u64;
+        self.packets_to_client.fetch_add(1, Ordering::Relaxed);
+        self.bytes_to_client.fetch_add(bytes, Ordering::Relaxed);
+        self.last_to_client
+            .store(self.count_packet(), Ordering::Relaxed);
+        metrics::PACKETS_TO_CLIENT.inc();
+        metrics::BYTES_TO_CLIENT.add(bytes);
+    }
+
+    /// Count a failed send to the game server.
+    pub fn record_send_failure_to_game(&self) {
+        self.send_failures_to_game.fetch_add(1, Ordering::Relaxed);
+        metrics::SEND_ERRORS_TO_GAME.inc();
+    }
+
+    /// Count a failed send to the browser.
+    pub fn record_send_failure_to_client(&self) {
+        self.send_failures_to_client.fetch_add(1, Ordering::Relaxed);
+        metrics::SEND_ERRORS_TO_CLIENT.inc();
+    }
+
+    /// Count a packet discarded without sending.
+    pub fn record_dropped(&self) {
+        self.dropped_packets.fetch_add(1, Ordering::Relaxed);
+        metrics::DROPPED_PACKETS.inc();
+    }
+
+    /// Update the packet rate window; returns the current time in ms.
+    fn count_packet(&self) -> u64 {
+        let now = unix_millis();
+        let second = now / 1000;
+
+        let mut rate = self.rate.lock();
+        if rate.second != second {
+            rate.second = second;
+            rate.count = 0;
+        }
+        rate.count += 1;
+        rate.peak = rate.peak.max(rate.count);
+
+        now
+    }
+
+    /// Copy the current values.
+    pub fn snapshot(&self) -> BridgeStatsSnapshot {
+        let nonzero = |value: u64| (value != 0).then_some(value);
+
+        BridgeStatsSnapshot {
+            client_id: self.client_id.clone(),
+            local_addr: self.local_addr,
+            transport: self.transport,
+            created_at: self.created_at,
+            packets_to_game: self.packets_to_game.load(Ordering::Relaxed),
+            bytes_to_game: self.bytes_to_game.load(Ordering::Relaxed),
+            packets_to_client: self.packets_to_client.load(Ordering::Relaxed),
+            bytes_to_client: self.bytes_to_client.load(Ordering::Relaxed),
+            send_failures_to_game: self.send_failures_to_game.load(Ordering::Relaxed),
+            send_failures_to_client: self.send_failures_to_client.load(Ordering::Relaxed),
+            dropped_packets: self.dropped_packets.load(Ordering::Relaxed),
+            last_to_game: nonzero(self.last_to_game.load(Ordering::Relaxed)),
+            last_to_client: nonzero(self.last_to_client.load(Ordering::Relaxed)),
+            peak_packet_rate: self.rate.lock().peak,
+        }
+    }
+}
+
+fn unix_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| u64::try_from(d.as_millis()).unwrap_or(u64::MAX))
+}
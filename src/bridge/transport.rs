@@ -0,0 +1,314 @@
+//! Transports carrying game packets between the bridge and the browser.
+//!
+//! WebRTC data channels are preferred; a WebSocket carrying binary frames is
+//! the fallback for browsers that cannot establish a peer connection.
+//! WebTransport sessions carry packets as HTTP/3 datagrams.
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use bytes::Bytes;
+use futures::future::BoxFuture;
+use futures::{Sink, SinkExt};
+use parking_lot::Mutex;
+use tokio::sync::mpsc;
+use tokio_tungstenite::tungstenite::Message;
+use webrtc::data_channel::data_channel_message::DataChannelMessage;
+use webrtc::data_channel::RTCDataChannel;
+
+/// Datagram send and receive buffer of WebTransport connections
+pub const DATAGRAM_BUFFER_SIZE: usize = 1024 * 1024;
+
+pub type TransportError = Box<dyn std::error::Error + Send + Sync>;
+
+/// Called with each packet received from the browser
+pub type PacketHandler = Arc<dyn Fn(Bytes) -> BoxFuture<'static, ()> + Send + Sync>;
+
+/// Called once when the browser side goes away
+pub type CloseHandler = Box<dyn FnOnce() + Send>;
+
+/// Packet transport to and from the browser.
+pub trait Transport: Send + Sync {
+    /// Short name for logs and statistics.
+    fn name(&self) -> &'static str;
+
+    /// Bytes queued for the browser but not sent yet.
+    fn buffered_amount(&self) -> BoxFuture<'_, usize>;
+
+    /// Largest packet the transport can carry, if limited.
+    fn max_packet_size(&self) -> Option<usize> {
+        None
+    }
+
+    /// Send a packet to the browser.
+    fn send<'a>(&'a self, packet: &'a Bytes) -> BoxFuture<'a, Result<(), TransportError>>;
+
+    /// Register the handler of packets from the browser.
+    fn on_packet(&self, handler: PacketHandler);
+
+    /// Register the handler called when the transport closes or fails.
+    fn on_close(&self, handler: CloseHandler);
+}
+
+/// A pair of WebRTC data channels.
+///
+/// Uses two channels to match the original client expectations:
+/// - `write` channel: proxy sends TO browser (server -> client)
+/// - `read` channel: proxy receives FROM browser (client -> server)
+///
+/// Clients asking for a single channel get the same channel as both.
+pub struct DataChannelTransport {
+    write_channel: Arc<RTCDataChannel>,
+    read_channel: Arc<RTCDataChannel>,
+}
+
+impl DataChannelTransport {
+    pub fn new(write_channel: Arc<RTCDataChannel>, read_channel: Arc<RTCDataChannel>) -> Self {
+        Self {
+            write_channel,
+            read_channel,
+        }
+    }
+}
+
+impl Transport for DataChannelTransport {
+    fn name(&self) -> &'static str {
+        "webrtc"
+    }
+
+    fn buffered_amount(&self) -> BoxFuture<'_, usize> {
+        Box::pin(self.write_channel.buffered_amount())
+    }
+
+    fn send<'a>(&'a self, packet: &'a Bytes) -> BoxFuture<'a, Result<(), TransportError>> {
+        Box::pin(async move {
+            self.write_channel.send(packet).await?;
+            Ok(())
+        })
+    }
+
+    fn on_packet(&self, handler: PacketHandler) {
+        self.read_channel
+            .on_message(Box::new(move |msg: DataChannelMessage| handler(msg.data)));
+    }
+
+    fn on_close(&self, handler: CloseHandler) {
+        // Whichever of close and error comes first
+        let handler = Arc::new(Mutex::new(Some(handler)));
+
+        let on_close = handler.clone();
+        self.read_channel.on_close(Box::new(move || {
+            if let Some(handler) = on_close.lock().take() {
+                handler();
+            }
+            Box::pin(async {})
+        }));
+
+        self.read_channel.on_error(Box::new(move |e| {
+            eprintln!("[WEBXASH] Read channel error: {e}");
+            if let Some(handler) = handler.lock().take() {
+                handler();
+            }
+            Box::pin(async {})
+        }));
+    }
+}
+
+/// Binary frames on the signaling WebSocket.
+///
+/// Frames from the browser are read by the signaling loop and handed over
+/// with [`WebSocketTransport::deliver`]. Frames to the browser go through a
+/// writer task so a slow browser shows up as a growing buffered amount
+/// instead of blocking the bridge.
+pub struct WebSocketTransport {
+    outgoing: mpsc::UnboundedSender<Bytes>,
+    /// Bytes handed to the writer task and not written yet
+    buffered: Arc<AtomicUsize>,
+    packet_handler: Mutex<Option<PacketHandler>>,
+    close_handler: Mutex<Option<CloseHandler>>,
+}
+
+impl WebSocketTransport {
+    /// Start writing binary frames to a WebSocket sink shared with the signaling.
+    pub fn new<S>(sink: Arc<tokio::sync::Mutex<S>>) -> Arc<Self>
+    where
+        S: Sink<Message> + Unpin + Send + 'static,
+    {
+        let (outgoing, mut queue) = mpsc::unbounded_channel::<Bytes>();
+        let buffered = Arc::new(AtomicUsize::new(0));
+
+        let transport = Arc::new(Self {
+            outgoing,
+            buffered: buffered.clone(),
+            packet_handler: Mutex::new(None),
+            close_handler: Mutex::new(None),
+        });
+
+        let weak = Arc::downgrade(&transport);
+        tokio::spawn(async move {
+            while let Some(packet) = queue.recv().await {
+                buffered.fetch_sub(packet.len(), Ordering::Relaxed);
+                if sink
+                    .lock()
+                    .await
+                    .send(Message::Binary(packet.to_vec()))
+                    .await
+                    .is_err()
+                {
+                    if let Some(transport) = weak.upgrade() {
+                        transport.close();
+                    }
+                    break;
+                }
+            }
+        });
+
+        transport
+    }
+
+    /// Pass a binary frame from the browser to the bridge.
+    pub async fn deliver(&self, packet: Bytes) {
+        let handler = self.packet_handler.lock().clone();
+        if let Some(handler) = handler {
+            handler(packet).await;
+        }
+    }
+
+    /// Signal that the WebSocket is gone.
+    pub fn close(&self) {
+        if let Some(handler) = self.close_handler.lock().take() {
+            handler();
+        }
+    }
+}
+
+impl Transport for WebSocketTransport {
+    fn name(&self) -> &'static str {
+        "websocket"
+    }
+
+    fn buffered_amount(&self) -> BoxFuture<'_, usize> {
+        Box::pin(async { self.buffered.load(Ordering::Relaxed) })
+    }
+
+    fn send<'a>(&'a self, packet: &'a Bytes) -> BoxFuture<'a, Result<(), TransportError>> {
+        Box::pin(async move {
+            self.buffered.fetch_add(packet.len(), Ordering::Relaxed);
+            self.outgoing
+                .send(packet.clone())
+                .map_err(|_| "WebSocket writer has stopped")?;
+            Ok(())
+        })
+    }
+
+    fn on_packet(&self, handler: PacketHandler) {
+        *self.packet_handler.lock() = Some(handler);
+    }
+
+    fn on_close(&self, handler: CloseHandler) {
+        *self.close_handler.lock() = Some(handler);
+    }
+}
+
+/// HTTP/3 datagrams of a WebTransport session.
+///
+/// Each datagram starts with the quarter stream id of the session's CONNECT
+/// stream (RFC 9297), followed by one game packet.
+pub struct DatagramTransport {
+    connection: quinn::Connection,
+    /// Quarter stream id of the session
+    session: u64,
+    /// `session` encoded as a QUIC variable-length integer
+    prefix: Bytes,
+}
+
+impl DatagramTransport {
+    /// Wrap a connection; `session_id` is the stream id of the CONNECT request.
+    pub fn new(connection: quinn::Connection, session_id: u64) -> Self {
+        let session = session_id / 4;
+        Self {
+            connection,
+            session,
+            prefix: encode_varint(session).into(),
+        }
+    }
+}
+
+impl Transport for DatagramTransport {
+    fn name(&self) -> &'static str {
+        "webtransport"
+    }
+
+    fn buffered_amount(&self) -> BoxFuture<'_, usize> {
+        let space = self.connection.datagram_send_buffer_space();
+        Box::pin(async move { DATAGRAM_BUFFER_SIZE.saturating_sub(space) })
+    }
+
+    fn max_packet_size(&self) -> Option<usize> {
+        // Unsupported datagrams fail on send
+        self.connection
+            .max_datagram_size()
+            .map(|size| size.saturating_sub(self.prefix.len()))
+    }
+
+    fn send<'a>(&'a self, packet: &'a Bytes) -> BoxFuture<'a, Result<(), TransportError>> {
+        Box::pin(async move {
+            let mut datagram = Vec::with_capacity(self.prefix.len() + packet.len());
+            datagram.extend_from_slice(&self.prefix);
+            datagram.extend_from_slice(packet);
+            self.connection.send_datagram(datagram.into())?;
+            Ok(())
+        })
+    }
+
+    fn on_packet(&self, handler: PacketHandler) {
+        let connection = self.connection.clone();
+        let session = self.session;
+
+        tokio::spawn(async move {
+            // Ends when the connection closes
+            while let Ok(datagram) = connection.read_datagram().await {
+                match decode_varint(&datagram) {
+                    Some((id, len)) if id == session => handler(datagram.slice(len..)).await,
+                    _ => {}
+                }
+            }
+        });
+    }
+
+    fn on_close(&self, handler: CloseHandler) {
+        let connection = self.connection.clone();
+        tokio::spawn(async move {
+            connection.closed().await;
+            handler();
+        });
+    }
+}
+
+/// Encode a QUIC variable-length integer (RFC 9000, section 16).
+fn encode_varint(value: u64) -> Vec<u8> {
+    let (len, tag) = match value {
+        0..=0x3f => (1, 0x00),
+        0x40..=0x3fff => (2, 0x40),
+        0x4000..=0x3fff_ffff => (4, 0x80),
+        _ => (8, 0xc0),
+    };
+
+    let mut bytes = value.to_be_bytes()[8 - len..].to_vec();
+    bytes[0] |= tag;
+    bytes
+}
+
+/// Decode a QUIC variable-length integer; returns the value and its length.
+fn decode_varint(data: &[u8]) -> Option<(u64, usize)> {
+    let first = *data.first()?;
+    let len = 1 << (first >> 6);
+    let bytes = data.get(..len)?;
+
+    let value = bytes[1..]
+        .iter()
+        .fold(u64::from(first & 0x3f), |value, &byte| {
+            (value << 8) | u64::from(byte)
+        });
+    Some((value, len))
+}
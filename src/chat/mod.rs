@@ -0,0 +1,175 @@
+//! Chat bridge from the web portal into the game.
+//!
+//! Posted messages are rate limited, queued here and printed to the players
+//! on the game thread. In-game chat reaches the web through the event feed.
+
+use std::collections::{HashMap, VecDeque};
+use std::net::IpAddr;
+use std::time::{Duration, Instant};
+
+use once_cell::sync::Lazy;
+use parking_lot::Mutex;
+
+use crate::events::{self, EventKind};
+
+/// Longest accepted message, in characters (the client chat line is short)
+const MAX_MESSAGE_CHARS: usize = 127;
+
+/// Longest accepted sender name, in characters (as for player names)
+const MAX_NAME_CHARS: usize = 31;
+
+/// Messages a single client address may post in a burst
+const SENDER_BURST: f64 = 3.0;
+/// Sustained messages per second of a single client address
+const SENDER_RATE: f64 = 0.5;
+
+/// Messages all senders together may post in a burst
+const GLOBAL_BURST: f64 = 10.0;
+/// Sustained messages per second of all senders together
+const GLOBAL_RATE: f64 = 2.0;
+
+/// Senders idle this long are forgotten
+const SENDER_IDLE: Duration = Duration::from_secs(30);
+
+/// Most messages waiting for the game thread
+const MAX_QUEUED: usize = 32;
+
+/// Chat lines waiting to be printed in-game
+static OUTGOING: Lazy<Mutex<VecDeque<String>>> = Lazy::new(|| Mutex::new(VecDeque::new()));
+
+static LIMITER: Lazy<Mutex<RateLimiter>> = Lazy::new(|| Mutex::new(RateLimiter::new()));
+
+/// Reasons a chat post is refused
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PostError {
+    EmptyName,
+    EmptyMessage,
+    RateLimited,
+}
+
+impl std::fmt::Display for PostError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::EmptyName => "name is empty",
+            Self::EmptyMessage => "message is empty",
+            Self::RateLimited => "too many messages, slow down",
+        })
+    }
+}
+
+impl std::error::Error for PostError {}
+
+/// Queue a message from the web portal for the game and publish it as an event.
+///
+/// `prefix` marks the sender as a web user in-game (e.g. `[WEB]`). Rate
+/// limits apply per `client` address, since the name is the client's choice.
+pub fn post(prefix: &str, client: IpAddr, name: &str, message: &str) -> Result<(), PostError> {
+    let name = sanitize(name, MAX_NAME_CHARS);
+    let message = sanitize(message, MAX_MESSAGE_CHARS);
+    if name.is_empty() {
+        return Err(PostError::EmptyName);
+    }
+    if message.is_empty() {
+        return Err(PostError::EmptyMessage);
+    }
+    if !LIMITER.lock().allow(client) {
+        return Err(PostError::RateLimited);
+    }
+
+    let name = if prefix.is_empty() {
+        name
+    } else {
+        format!("{prefix} {name}")
+    };
+
+    {
+        let mut outgoing = OUTGOING.lock();
+        if outgoing.len() >= MAX_QUEUED {
+            return Err(PostError::RateLimited);
+        }
+        outgoing.push_back(format!("{name}: {message}"));
+    }
+
+    events::publish(EventKind::Chat {
+        slot: 0,
+        name,
+        team: false,
+        message,
+        portal: true,
+    });
+    Ok(())
+}
+
+/// Take the messages waiting to be printed in-game.
+pub fn take_outgoing() -> Vec<String> {
+    OUTGOING.lock().drain(..).collect()
+}
+
+/// Remove control characters (including the client's color codes), trim
+/// and truncate.
+fn sanitize(text: &str, max_chars: usize) -> String {
+    text.chars()
+        .filter(|c| !c.is_control())
+        .take(max_chars)
+        .collect::<String>()
+        .trim()
+        .to_string()
+}
+
+/// Token buckets per client address plus one shared by everybody
+struct RateLimiter {
+    global: TokenBucket,
+    senders: HashMap<IpAddr, TokenBucket>,
+}
+
+impl RateLimiter {
+    fn new() -> Self {
+        Self {
+            global: TokenBucket::new(GLOBAL_BURST),
+            senders: HashMap::new(),
+        }
+    }
+
+    /// Take a token for `sender`; `false` if the sender or everybody is over the limit.
+    fn allow(&mut self, sender: IpAddr) -> bool {
+        let now = Instant::now();
+        self.senders
+            .retain(|_, bucket| now.duration_since(bucket.updated) < SENDER_IDLE);
+
+        let bucket = self
+            .senders
+            .entry(sender)
+            .or_insert_with(|| TokenBucket::new(SENDER_BURST));
+        if !bucket.try_take(now, SENDER_BURST, SENDER_RATE) {
+            return false;
+        }
+        self.global.try_take(now, GLOBAL_BURST, GLOBAL_RATE)
+    }
+}
+
+struct TokenBucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+impl TokenBucket {
+    fn new(burst: f64) -> Self {
+        Self {
+            tokens: burst,
+            updated: Instant::now(),
+        }
+    }
+
+    fn try_take(&mut self, now: Instant, burst: f64, rate: f64) -> bool {
+        let elapsed = now.duration_since(self.updated).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * rate).min(burst);
+        self.updated = now;
+
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            true
+        } else {
+            false
+        }
+    }
+}
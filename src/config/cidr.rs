@@ -0,0 +1,197 @@
+//! IP network (CIDR) matching for address allowlists.
+
+use std::fmt;
+use std::net::IpAddr;
+use std::str::FromStr;
+
+/// An IP network in CIDR notation (e.g. `10.0.0.0/8`, `::1/128`).
+///
+/// A bare address without a prefix length matches that single address.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IpCidr {
+    addr: IpAddr,
+    prefix_len: u8,
+}
+
+impl IpCidr {
+    /// Check whether an address falls inside this network.
+    ///
+    /// IPv4-mapped IPv6 addresses (`::ffff:a.b.c.d`) are matched against IPv4 networks.
+    pub fn contains(&self, ip: IpAddr) -> bool {
+        let ip = match ip {
+            IpAddr::V6(v6) => v6.to_ipv4_mapped().map_or(ip, IpAddr::V4),
+            IpAddr::V4(_) => ip,
+        };
+
+        match (self.addr, ip) {
+            (IpAddr::V4(net), IpAddr::V4(ip)) => prefix_matches(
+                u128::from(u32::from(net)),
+                u128::from(u32::from(ip)),
+                32,
+                self.prefix_len,
+            ),
+            (IpAddr::V6(net), IpAddr::V6(ip)) => {
+                prefix_matches(u128::from(net), u128::from(ip), 128, self.prefix_len)
+            }
+            _ => false,
+        }
+    }
+
+    /// Parse a comma or whitespace separated list of networks.
+    ///
+    /// Invalid entries are skipped and reported on stderr.
+    pub fn parse_list(s: &str) -> Vec<Self> {
+        s.split(|c: char| c == ',' || c.is_whitespace())
+            .filter(|entry| !entry.is_empty())
+            .filter_map(|entry| match entry.parse() {
+                Ok(cidr) => Some(cidr),
+                Err(e) => {
+                    eprintln!("[WEBXASH] Ignoring invalid network '{entry}': {e}");
+                    None
+                }
+            })
+            .collect()
+    }
+}
+
+/// Compare the first `prefix_len` bits of two addresses that are `bits` wide.
+fn prefix_matches(net: u128, ip: u128, bits: u8, prefix_len: u8) -> bool {
+    if prefix_len == 0 {
+        return true;
+    }
+    let shift = u32::from(bits - prefix_len);
+    (net >> shift) == (ip >> shift)
+}
+
+/// Matches any address in one of the given networks.
+pub fn contains_any(networks: &[IpCidr], ip: IpAddr) -> bool {
+    networks.iter().any(|net| net.contains(ip))
+}
+
+impl FromStr for IpCidr {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (addr_str, prefix_str) = match s.split_once('/') {
+            Some((addr, prefix)) => (addr, Some(prefix)),
+            None => (s, None),
+        };
+
+        let addr: IpAddr = addr_str
+            .parse()
+            .map_err(|_| format!("invalid IP address '{addr_str}'"))?;
+        let max_len = if addr.is_ipv4() { 32 } else { 128 };
+
+        let prefix_len = match prefix_str {
+            Some(p) => p
+                .parse::<u8>()
+                .ok()
+                .filter(|len| *len <= max_len)
+                .ok_or_else(|| format!("invalid prefix length '{p}'"))?,
+            None => max_len,
+        };
+
+        Ok(Self { addr, prefix_len })
+    }
+}
+
+impl fmt::Display for IpCidr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}", self.addr, self.prefix_len)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cidr(s: &str) -> IpCidr {
+        s.parse().unwrap()
+    }
+
+    fn ip(s: &str) -> IpAddr {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn parses_networks_and_bare_addresses() {
+        assert_eq!(cidr("10.0.0.0/8").to_string(), "10.0.0.0/8");
+        assert_eq!(cidr("192.0.2.1").to_string(), "192.0.2.1/32");
+        assert_eq!(cidr("2001:db8::/32").to_string(), "2001:db8::/32");
+        assert_eq!(cidr("::1").to_string(), "::1/128");
+    }
+
+    #[test]
+    fn rejects_malformed_networks() {
+        for s in [
+            "",
+            "/8",
+            "10.0.0.0/",
+            "10.0.0.0/33",
+            "::/129",
+            "10.0.0.0/-1",
+            "10.0.0.0/8/8",
+            "10.0.0/8",
+            "example.com",
+            "10.0.0.0 /8",
+        ] {
+            assert!(s.parse::<IpCidr>().is_err(), "{s}");
+        }
+    }
+
+    #[test]
+    fn matches_prefix() {
+        let net = cidr("10.0.0.0/8");
+        assert!(net.contains(ip("10.0.0.0")));
+        assert!(net.contains(ip("10.255.255.255")));
+        assert!(!net.contains(ip("11.0.0.0")));
+        assert!(!net.contains(ip("9.255.255.255")));
+
+        let net = cidr("192.168.1.0/23");
+        assert!(net.contains(ip("192.168.0.1")));
+        assert!(!net.contains(ip("192.168.2.1")));
+
+        let net = cidr("2001:db8::/32");
+        assert!(net.contains(ip("2001:db8:ffff::1")));
+        assert!(!net.contains(ip("2001:db9::1")));
+    }
+
+    #[test]
+    fn host_bits_of_the_network_are_ignored() {
+        assert!(cidr("10.1.2.3/8").contains(ip("10.200.0.1")));
+    }
+
+    #[test]
+    fn bare_address_matches_only_itself() {
+        let net = cidr("192.0.2.1");
+        assert!(net.contains(ip("192.0.2.1")));
+        assert!(!net.contains(ip("192.0.2.2")));
+    }
+
+    #[test]
+    fn zero_prefix_matches_its_family_only() {
+        assert!(cidr("0.0.0.0/0").contains(ip("203.0.113.7")));
+        assert!(!cidr("0.0.0.0/0").contains(ip("2001:db8::1")));
+        assert!(cidr("::/0").contains(ip("2001:db8::1")));
+    }
+
+    #[test]
+    fn ipv4_mapped_addresses_match_ipv4_networks() {
+        assert!(cidr("10.0.0.0/8").contains(ip("::ffff:10.1.2.3")));
+        assert!(!cidr("10.0.0.0/8").contains(ip("::ffff:11.1.2.3")));
+        // An IPv4 address never matches an IPv6 network
+        assert!(!cidr("::ffff:0:0/96").contains(ip("10.1.2.3")));
+    }
+
+    #[test]
+    fn parse_list_skips_invalid_entries() {
+        let list = IpCidr::parse_list("10.0.0.0/8, bogus 192.0.2.1,,::1/200\t2001:db8::/32");
+        assert_eq!(
+            list,
+            vec![cidr("10.0.0.0/8"), cidr("192.0.2.1"), cidr("2001:db8::/32")]
+        );
+        assert!(contains_any(&list, ip("192.0.2.1")));
+        assert!(!contains_any(&list, ip("192.0.2.2")));
+        assert!(!contains_any(&[], ip("192.0.2.1")));
+    }
+}
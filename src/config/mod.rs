@@ -2,6 +2,12 @@
 //!
 //! Configuration is set via webxash_* cvars.
 
+mod cidr;
+
+use std::time::Duration;
+
+pub use cidr::{contains_any, IpCidr};
+
 /// Plugin configuration
 #[derive(Debug, Clone)]
 pub struct PluginConfig {
@@ -11,6 +17,153 @@ pub struct PluginConfig {
     pub game_port: u16,
     /// Public IP for NAT traversal (from webxash_public_ip cvar)
     pub public_ip: Option<String>,
+    /// Reverse proxies allowed to set client address headers (from `webxash_trusted_proxies` cvar)
+    pub trusted_proxies: Vec<IpCidr>,
+    /// Load balancers that send a PROXY protocol header (from `webxash_proxy_protocol` cvar)
+    pub proxy_protocol_sources: Vec<IpCidr>,
+    /// Browser origins allowed to connect, empty for any (from `webxash_allowed_origins` cvar)
+    pub allowed_origins: Vec<String>,
+    /// URL prefix all routes are mounted under, e.g. `/play` (from `webxash_base_path` cvar)
+    pub base_path: String,
+    /// Game servers listed by `/servers` (from `webxash_server_list` cvar)
+    pub directory_servers: Vec<DirectoryServer>,
+    /// Bearer token for the admin API, disabled when unset (from `webxash_admin_token` cvar)
+    pub admin_token: Option<String>,
+    /// Users of the web RCON console (from `webxash_rcon_users` cvar)
+    pub rcon_users: Vec<RconUser>,
+    /// Bearer token for posting web chat, disabled when unset (from `webxash_chat_token` cvar)
+    pub chat_token: Option<String>,
+    /// Name prefix of web chat shown in-game (from `webxash_chat_prefix` cvar)
+    pub chat_prefix: String,
+    /// Delivery of the game data channels unless the client asks otherwise (from `webxash_channel_reliability` cvar)
+    pub channel_reliability: ChannelReliability,
+    /// UDP port of the WebTransport listener, disabled when unset (from `webxash_webtransport_port` cvar)
+    pub webtransport_port: Option<u16>,
+    /// PEM certificate chain for TLS, self-signed when unset (from `webxash_tls_cert` cvar)
+    pub tls_cert: Option<String>,
+    /// PEM private key of `tls_cert` (from `webxash_tls_key` cvar)
+    pub tls_key: Option<String>,
+    /// STUN and TURN server URLs for both peers (from `webxash_ice_servers` cvar)
+    pub ice_servers: Vec<String>,
+    /// Static username of the TURN servers (from `webxash_turn_username` cvar)
+    pub turn_username: Option<String>,
+    /// Static credential of the TURN servers (from `webxash_turn_credential` cvar)
+    pub turn_credential: Option<String>,
+    /// Shared secret for time-limited TURN REST credentials, preferred over the
+    /// static ones (from `webxash_turn_secret` cvar)
+    pub turn_secret: Option<String>,
+    /// Lifetime of TURN REST credentials (from `webxash_turn_ttl` cvar)
+    pub turn_credential_ttl: Duration,
+    /// UDP and TCP port of the embedded TURN server, disabled when unset (from `webxash_turn_port` cvar)
+    pub turn_port: Option<u16>,
+    /// Concurrent relay allocations per TURN client, 0 for no limit (from `webxash_turn_max_allocations` cvar)
+    pub turn_max_allocations: usize,
+    /// Relayed bytes per second per TURN client, 0 for no limit (from `webxash_turn_max_rate` cvar)
+    pub turn_max_rate: u64,
+    /// Local UDP ports of peer connections (from `webxash_ice_ports` cvar)
+    pub ice_ports: IcePorts,
+    /// Age at which the DTLS certificate is replaced, never if unset (from `webxash_dtls_rotation` cvar)
+    pub dtls_rotation: Option<Duration>,
+}
+
+/// A web RCON user
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RconUser {
+    /// Name recorded in the audit log
+    pub name: String,
+    /// Token the user authenticates with
+    pub token: String,
+    /// Allowed command names (lowercase), empty for all commands
+    pub allowed_commands: Vec<String>,
+}
+
+impl RconUser {
+    /// Check whether the user may run `command`.
+    ///
+    /// Restricted users may not chain commands with `;`, which would bypass
+    /// the allowlist.
+    pub fn allows(&self, command: &str) -> bool {
+        if self.allowed_commands.is_empty() {
+            return true;
+        }
+        if command.contains(';') {
+            return false;
+        }
+
+        let name = command.split_whitespace().next().unwrap_or("");
+        self.allowed_commands
+            .iter()
+            .any(|allowed| allowed.eq_ignore_ascii_case(name))
+    }
+}
+
+/// Delivery guarantees of the game data channels
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ChannelReliability {
+    /// Unordered without retransmissions; the game netchan handles loss itself
+    #[default]
+    Unreliable,
+    /// Ordered and fully reliable, as in the original proxy
+    Reliable,
+}
+
+impl ChannelReliability {
+    /// Parse `unreliable` or `reliable` (case-insensitive).
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "unreliable" => Some(Self::Unreliable),
+            "reliable" => Some(Self::Reliable),
+            _ => None,
+        }
+    }
+
+    /// Name used in cvars and signaling messages.
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Unreliable => "unreliable",
+            Self::Reliable => "reliable",
+        }
+    }
+}
+
+/// Local UDP ports used by peer connections for ICE
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum IcePorts {
+    /// A random ephemeral port per peer connection
+    #[default]
+    Any,
+    /// One port shared by all peer connections
+    Single(u16),
+    /// A random port from an inclusive range per peer connection
+    Range(u16, u16),
+}
+
+impl IcePorts {
+    /// Parse a port (`27016`) or an inclusive port range (`50000-50100`).
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.trim().split_once('-') {
+            Some((min, max)) => {
+                let min = min.trim().parse().ok().filter(|&min| min != 0)?;
+                let max = max.trim().parse().ok().filter(|&max| max >= min)?;
+                Some(Self::Range(min, max))
+            }
+            None => s
+                .trim()
+                .parse()
+                .ok()
+                .filter(|&port| port != 0)
+                .map(Self::Single),
+        }
+    }
+}
+
+/// A game server listed in the server directory
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DirectoryServer {
+    /// Game server address (`host:port`)
+    pub address: String,
+    /// Signaling URL override, derived from the address when unset
+    pub signaling_url: Option<String>,
 }
 
 impl Default for PluginConfig {
@@ -19,6 +172,147 @@ impl Default for PluginConfig {
             http_port: 27015,
             game_port: 27015,
             public_ip: None,
+            trusted_proxies: Vec::new(),
+            proxy_protocol_sources: Vec::new(),
+            allowed_origins: Vec::new(),
+            base_path: String::new(),
+            directory_servers: Vec::new(),
+            admin_token: None,
+            rcon_users: Vec::new(),
+            chat_token: None,
+            chat_prefix: "[WEB]".to_string(),
+            channel_reliability: ChannelReliability::default(),
+            webtransport_port: None,
+            tls_cert: None,
+            tls_key: None,
+            ice_servers: Vec::new(),
+            turn_username: None,
+            turn_credential: None,
+            turn_secret: None,
+            turn_credential_ttl: Duration::from_hours(24),
+            turn_port: None,
+            turn_max_allocations: 4,
+            turn_max_rate: 256 * 1024,
+            ice_ports: IcePorts::default(),
+            dtls_rotation: Some(Duration::from_hours(30 * 24)),
         }
     }
 }
+
+impl PluginConfig {
+    /// Address of the local game server.
+    pub fn game_server_addr(&self) -> String {
+        // The game server is running on the same machine
+        format!("127.0.0.1:{}", self.game_port)
+    }
+}
+
+/// Normalize a URL base path to the `/prefix` form (leading slash, no trailing slash).
+///
+/// An empty string or `/` means routes are served from the root.
+pub fn normalize_base_path(s: &str) -> String {
+    let trimmed = s.trim().trim_matches('/');
+    if trimmed.is_empty() {
+        String::new()
+    } else {
+        format!("/{trimmed}")
+    }
+}
+
+/// Parse a comma or whitespace separated list of origins (e.g. `https://play.example.com`).
+pub fn parse_origin_list(s: &str) -> Vec<String> {
+    s.split(|c: char| c == ',' || c.is_whitespace())
+        .map(|origin| origin.trim_end_matches('/'))
+        .filter(|origin| !origin.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+/// Parse a comma or whitespace separated list of ICE server URLs
+/// (e.g. `stun:stun.example.com:3478,turn:turn.example.com:3478?transport=tcp`).
+pub fn parse_ice_server_list(s: &str) -> Vec<String> {
+    s.split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|url| !url.is_empty())
+        .filter(|url| {
+            let valid = ["stun:", "stuns:", "turn:", "turns:"]
+                .iter()
+                .any(|scheme| url.to_ascii_lowercase().starts_with(scheme));
+            if !valid {
+                eprintln!("[WEBXASH] Ignoring invalid ICE server URL: {url}");
+            }
+            valid
+        })
+        .map(str::to_string)
+        .collect()
+}
+
+/// Check whether an ICE server URL needs TURN credentials.
+pub fn is_turn_url(url: &str) -> bool {
+    let url = url.to_ascii_lowercase();
+    url.starts_with("turn:") || url.starts_with("turns:")
+}
+
+/// Parse a comma or whitespace separated server list.
+///
+/// Entries are `host:port`, optionally followed by `=` and the server's signaling
+/// URL (e.g. `cs2.example.com:27016=wss://cs2.example.com/play/ws`).
+pub fn parse_server_list(s: &str) -> Vec<DirectoryServer> {
+    s.split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|entry| !entry.is_empty())
+        .filter_map(|entry| {
+            let (address, signaling_url) = match entry.split_once('=') {
+                Some((address, url)) => (address, Some(url.to_string())),
+                None => (entry, None),
+            };
+
+            let valid = address
+                .rsplit_once(':')
+                .is_some_and(|(host, port)| !host.is_empty() && port.parse::<u16>().is_ok());
+            if !valid {
+                eprintln!("[WEBXASH] Ignoring invalid server list entry: {entry}");
+                return None;
+            }
+
+            Some(DirectoryServer {
+                address: address.to_string(),
+                signaling_url,
+            })
+        })
+        .collect()
+}
+
+/// Parse a comma separated list of web RCON users.
+///
+/// Entries are `name:token`, optionally followed by `:` and a `|` separated
+/// command allowlist (e.g. `mod:s3cret:status|kick|changelevel`).
+pub fn parse_rcon_users(s: &str) -> Vec<RconUser> {
+    s.split(',')
+        .map(str::trim)
+        .filter(|entry| !entry.is_empty())
+        .filter_map(|entry| {
+            let mut parts = entry.splitn(3, ':');
+            let name = parts.next().unwrap_or("").trim();
+            let token = parts.next().unwrap_or("").trim();
+            if name.is_empty() || token.is_empty() {
+                eprintln!(
+                    "[WEBXASH] Ignoring invalid RCON user entry (expected name:token[:commands])"
+                );
+                return None;
+            }
+
+            let allowed_commands = parts
+                .next()
+                .unwrap_or("")
+                .split('|')
+                .map(|command| command.trim().to_lowercase())
+                .filter(|command| !command.is_empty() && command != "*")
+                .collect();
+
+            Some(RconUser {
+                name: name.to_string(),
+                token: token.to_string(),
+                allowed_commands,
+            })
+        })
+        .collect()
+}
@@ -0,0 +1,55 @@
+//! Server console output capture.
+//!
+//! The engine hooks (`pfnServerPrint`, `pfnAlertMessage`) publish every line
+//! here; web consoles subscribe to the stream.
+
+use once_cell::sync::Lazy;
+use serde::Serialize;
+use tokio::sync::broadcast;
+
+/// Lines buffered per subscriber before it starts missing output
+const CHANNEL_CAPACITY: usize = 256;
+
+/// Names of the engine `ALERT_TYPE` values (`at_notice` .. `at_logged`)
+const ALERT_KINDS: [&str; 6] = ["notice", "console", "aiconsole", "warning", "error", "log"];
+
+static OUTPUT: Lazy<broadcast::Sender<ConsoleLine>> =
+    Lazy::new(|| broadcast::channel(CHANNEL_CAPACITY).0);
+
+/// One piece of console output
+#[derive(Debug, Clone, Serialize)]
+pub struct ConsoleLine {
+    /// `print` for `pfnServerPrint`, otherwise the alert type (`console`, `log`, ...)
+    pub kind: &'static str,
+    pub text: String,
+}
+
+/// Whether anyone is listening; lets the hooks skip formatting otherwise.
+pub fn has_subscribers() -> bool {
+    OUTPUT.receiver_count() > 0
+}
+
+/// Subscribe to console output.
+pub fn subscribe() -> broadcast::Receiver<ConsoleLine> {
+    OUTPUT.subscribe()
+}
+
+/// Publish a `pfnServerPrint` message.
+pub fn publish_print(text: String) {
+    publish("print", text);
+}
+
+/// Publish a `pfnAlertMessage` message.
+pub fn publish_alert(alert_type: i32, text: String) {
+    let kind = usize::try_from(alert_type)
+        .ok()
+        .and_then(|index| ALERT_KINDS.get(index))
+        .copied()
+        .unwrap_or("console");
+    publish(kind, text);
+}
+
+fn publish(kind: &'static str, text: String) {
+    // Fails only without subscribers
+    let _ = OUTPUT.send(ConsoleLine { kind, text });
+}
@@ -0,0 +1,188 @@
+//! Live game event feed.
+//!
+//! Events are captured on the game thread (DLL hooks and HL log lines) and
+//! broadcast to the `/events` subscribers. The feed is public, so only parsed
+//! log lines are published; raw lines carry client addresses and rcon activity.
+
+use std::collections::HashSet;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use once_cell::sync::Lazy;
+use serde::Serialize;
+use tokio::sync::broadcast;
+
+use crate::game::{parse_log_line, LogEvent};
+
+/// Events buffered per subscriber before it starts missing events
+const CHANNEL_CAPACITY: usize = 512;
+
+static EVENTS: Lazy<broadcast::Sender<GameEvent>> =
+    Lazy::new(|| broadcast::channel(CHANNEL_CAPACITY).0);
+
+/// A game event with its capture time
+#[derive(Debug, Clone, Serialize)]
+pub struct GameEvent {
+    /// Unix time (ms)
+    pub time: u64,
+    #[serde(flatten)]
+    pub kind: EventKind,
+}
+
+/// Event payloads, serialized with a `type` tag
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum EventKind {
+    /// A client connected (`ClientConnect`)
+    Connect { slot: i32, name: String, web: bool },
+    /// A client disconnected (`ClientDisconnect`)
+    Disconnect { slot: i32, name: String },
+    /// `say` / `say_team` (`ClientCommand`), or a message posted from the web
+    Chat {
+        /// Player slot, 0 for web posts
+        slot: i32,
+        name: String,
+        team: bool,
+        message: String,
+        /// Posted through the web chat bridge
+        portal: bool,
+    },
+    /// A map was loaded (`ServerActivate`)
+    MapStart { map: String },
+    /// A map is being unloaded (`ServerDeactivate`)
+    MapEnd { map: String },
+    /// A player killed another (HL log line)
+    Kill {
+        killer: String,
+        victim: String,
+        weapon: String,
+    },
+    /// A player joined a team (HL log line)
+    Team { name: String, team: String },
+}
+
+impl EventKind {
+    /// Value of the `type` tag.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Connect { .. } => "connect",
+            Self::Disconnect { .. } => "disconnect",
+            Self::Chat { .. } => "chat",
+            Self::MapStart { .. } => "map_start",
+            Self::MapEnd { .. } => "map_end",
+            Self::Kill { .. } => "kill",
+            Self::Team { .. } => "team",
+        }
+    }
+}
+
+/// Event types a subscriber wants
+#[derive(Debug, Clone, Default)]
+pub struct EventFilter {
+    /// Accepted `type` tags, `None` for all
+    types: Option<HashSet<String>>,
+}
+
+impl EventFilter {
+    /// Parse a comma separated list of event types; empty accepts all.
+    pub fn parse(types: &str) -> Self {
+        let types: HashSet<String> = types
+            .split(',')
+            .map(|t| t.trim().to_lowercase())
+            .filter(|t| !t.is_empty())
+            .collect();
+        Self {
+            types: (!types.is_empty()).then_some(types),
+        }
+    }
+
+    pub fn matches(&self, event: &GameEvent) -> bool {
+        self.types
+            .as_ref()
+            .is_none_or(|types| types.contains(event.kind.name()))
+    }
+}
+
+/// Whether anyone is listening; lets the hooks skip work otherwise.
+pub fn has_subscribers() -> bool {
+    EVENTS.receiver_count() > 0
+}
+
+/// Subscribe to game events.
+pub fn subscribe() -> broadcast::Receiver<GameEvent> {
+    EVENTS.subscribe()
+}
+
+/// Publish an event.
+pub fn publish(kind: EventKind) {
+    let time = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| u64::try_from(d.as_millis()).unwrap_or(u64::MAX));
+
+    // Fails only without subscribers
+    let _ = EVENTS.send(GameEvent { time, kind });
+}
+
+/// Publish an HL log line if it is a kill or a team join; others are dropped.
+pub fn publish_log_line(line: &str) {
+    if let Some(kind) = log_event(line) {
+        publish(kind);
+    }
+}
+
+/// The feed event of a kill or team join log line.
+fn log_event(line: &str) -> Option<EventKind> {
+    match parse_log_line(line)? {
+        LogEvent::Kill {
+            killer,
+            victim,
+            weapon,
+        } => Some(EventKind::Kill {
+            killer: killer.name.to_string(),
+            victim: victim.name.to_string(),
+            weapon: weapon.to_string(),
+        }),
+        LogEvent::JoinTeam { player, team } => Some(EventKind::Team {
+            name: player.name.to_string(),
+            team: team.to_string(),
+        }),
+        LogEvent::Suicide { .. } => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn kill_and_team_lines() {
+        assert!(matches!(
+            log_event("\"Player<2><STEAM_0:1:1234><CT>\" killed \"Bot<3><BOT><TERRORIST>\" with \"ak47\""),
+            Some(EventKind::Kill { killer, victim, weapon })
+                if killer == "Player" && victim == "Bot" && weapon == "ak47"
+        ));
+        assert!(matches!(
+            log_event("\"Player<2><STEAM_0:1:1234><>\" joined team \"CT\""),
+            Some(EventKind::Team { name, team }) if name == "Player" && team == "CT"
+        ));
+    }
+
+    #[test]
+    fn say_lines_cannot_fake_events() {
+        assert!(log_event(
+            "\"Evil<5><STEAM_0:0:1><CT>\" say \" killed \"Victim<3><BOT><CT>\" with \"awp\""
+        )
+        .is_none());
+        assert!(log_event("\"Evil<5><STEAM_0:0:1><CT>\" say_team \" joined team \"CT\"").is_none());
+    }
+
+    #[test]
+    fn raw_log_lines_are_not_published() {
+        assert!(log_event(
+            "\"Player<2><STEAM_0:1:1234><>\" connected, address \"203.0.113.7:27005\""
+        )
+        .is_none());
+        assert!(
+            log_event("Rcon: \"rcon 1234 \"pass\" status\" from \"203.0.113.7:27005\"").is_none()
+        );
+    }
+}
@@ -0,0 +1,186 @@
+//! HL log line parsing.
+//!
+//! Only whole lines of the known formats are recognized. Player names cannot
+//! contain `"` (info strings do not allow it), so a quoted player ends at the
+//! next quote; chat text can contain anything, including quotes and what looks
+//! like other log lines.
+
+use std::ffi::c_int;
+
+/// A logged player (`"Name<userid><authid><team>"`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LogPlayer<'a> {
+    pub name: &'a str,
+    pub userid: c_int,
+    pub authid: &'a str,
+    /// Empty for spectators and unassigned players
+    pub team: &'a str,
+}
+
+/// A recognized log line
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LogEvent<'a> {
+    /// `"Killer<..>" killed "Victim<..>" with "weapon"`
+    Kill {
+        killer: LogPlayer<'a>,
+        victim: LogPlayer<'a>,
+        weapon: &'a str,
+    },
+    /// `"Player<..>" committed suicide with "weapon"`
+    Suicide {
+        player: LogPlayer<'a>,
+        weapon: &'a str,
+    },
+    /// `"Player<..>" joined team "team"`
+    JoinTeam {
+        player: LogPlayer<'a>,
+        team: &'a str,
+    },
+}
+
+/// Parse a kill, suicide or team join line; anything else is `None`.
+pub fn parse_log_line(line: &str) -> Option<LogEvent<'_>> {
+    let (subject, rest) = quoted(line.trim_end())?;
+    let player = parse_player(subject)?;
+
+    // Chat text is free-form, so it is never matched against other formats
+    if rest.starts_with(" say ") || rest.starts_with(" say_team ") {
+        return None;
+    }
+
+    if let Some(rest) = rest.strip_prefix(" killed ") {
+        let (victim, rest) = quoted(rest)?;
+        return Some(LogEvent::Kill {
+            killer: player,
+            victim: parse_player(victim)?,
+            weapon: last_quoted(rest.strip_prefix(" with ")?)?,
+        });
+    }
+    if let Some(rest) = rest.strip_prefix(" committed suicide with ") {
+        return Some(LogEvent::Suicide {
+            player,
+            weapon: last_quoted(rest)?,
+        });
+    }
+    if let Some(rest) = rest.strip_prefix(" joined team ") {
+        return Some(LogEvent::JoinTeam {
+            player,
+            team: last_quoted(rest)?,
+        });
+    }
+    None
+}
+
+/// Split `"value" rest` into the value and the rest.
+fn quoted(s: &str) -> Option<(&str, &str)> {
+    s.strip_prefix('"')?.split_once('"')
+}
+
+/// The value of `"value"` ending the line.
+fn last_quoted(s: &str) -> Option<&str> {
+    let (value, rest) = quoted(s)?;
+    rest.is_empty().then_some(value)
+}
+
+/// Parse `Name<userid><authid><team>`.
+fn parse_player(player: &str) -> Option<LogPlayer<'_>> {
+    let rest = player.strip_suffix('>')?;
+    let (rest, team) = rest.rsplit_once('<')?;
+    let (rest, authid) = rest.strip_suffix('>')?.rsplit_once('<')?;
+    let (name, userid) = rest.strip_suffix('>')?.rsplit_once('<')?;
+
+    Some(LogPlayer {
+        name,
+        userid: userid.parse().ok()?,
+        authid,
+        team,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn player<'a>(name: &'a str, userid: c_int, authid: &'a str, team: &'a str) -> LogPlayer<'a> {
+        LogPlayer {
+            name,
+            userid,
+            authid,
+            team,
+        }
+    }
+
+    #[test]
+    fn kill() {
+        assert_eq!(
+            parse_log_line(
+                "\"Player<2><STEAM_0:1:1234><CT>\" killed \"Bot<3><BOT><TERRORIST>\" with \"ak47\"\n"
+            ),
+            Some(LogEvent::Kill {
+                killer: player("Player", 2, "STEAM_0:1:1234", "CT"),
+                victim: player("Bot", 3, "BOT", "TERRORIST"),
+                weapon: "ak47",
+            })
+        );
+    }
+
+    #[test]
+    fn suicide_and_team_join() {
+        assert_eq!(
+            parse_log_line("\"Bot<3><BOT><TERRORIST>\" committed suicide with \"world\""),
+            Some(LogEvent::Suicide {
+                player: player("Bot", 3, "BOT", "TERRORIST"),
+                weapon: "world",
+            })
+        );
+        assert_eq!(
+            parse_log_line("\"Player<2><STEAM_0:1:1234><>\" joined team \"CT\""),
+            Some(LogEvent::JoinTeam {
+                player: player("Player", 2, "STEAM_0:1:1234", ""),
+                team: "CT",
+            })
+        );
+    }
+
+    #[test]
+    fn names_with_angle_brackets() {
+        assert_eq!(
+            parse_log_line("\"<a><1><X><CT><7><STEAM_0:0:1><CT>\" joined team \"TERRORIST\""),
+            Some(LogEvent::JoinTeam {
+                player: player("<a><1><X><CT>", 7, "STEAM_0:0:1", "CT"),
+                team: "TERRORIST",
+            })
+        );
+    }
+
+    #[test]
+    fn chat_cannot_spoof_events() {
+        for line in [
+            "\"Evil<5><STEAM_0:0:1><CT>\" say \" killed \"Bot<3><BOT><TERRORIST>\" with \"awp\"",
+            "\"Evil<5><STEAM_0:0:1><CT>\" say_team \" joined team \"SPECTATOR\"",
+            "\"Evil<5><STEAM_0:0:1><CT>\" say \"x\" killed \"Bot<3><BOT><TERRORIST>\" with \"awp\"",
+            "\"Evil<5><STEAM_0:0:1><CT>\" say \" committed suicide with \"world\"",
+        ] {
+            assert_eq!(parse_log_line(line), None, "{line}");
+        }
+    }
+
+    #[test]
+    fn rejects_other_and_malformed_lines() {
+        for line in [
+            "",
+            "World triggered \"Round_Start\"",
+            "\"Player<2><STEAM_0:1:1234><CT>\" connected, address \"203.0.113.7:27005\"",
+            "Rcon: \"rcon 1234 \"secret\" status\" from \"203.0.113.7:27005\"",
+            "\"Player<2><STEAM_0:1:1234><CT>\" killed \"Bot<3><BOT><TERRORIST>\" with \"ak47\" extra",
+            "\"Player<2><STEAM_0:1:1234><CT>\" killed \"Bot<x><BOT><TERRORIST>\" with \"ak47\"",
+            "\"Player<2><STEAM_0:1:1234><CT>\" killed \"Bot\" with \"ak47\"",
+            "\"Player<2><STEAM_0:1:1234><CT>\" killed \"Bot<3><BOT><TERRORIST>\"",
+            "\"Player<2><STEAM_0:1:1234><CT>\" joined team \"CT",
+            "Player<2><STEAM_0:1:1234><CT> joined team \"CT\"",
+            "L 10/18/2026 - 12:00:00: \"Player<2><STEAM_0:1:1234><>\" joined team \"CT\"",
+        ] {
+            assert_eq!(parse_log_line(line), None, "{line}");
+        }
+    }
+}
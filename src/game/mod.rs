@@ -0,0 +1,299 @@
+//! Game state shared between the game thread and the async runtime.
+//!
+//! The engine API may only be called from the game thread, so state is
+//! collected there and published as snapshots the HTTP handlers can read.
+
+use std::collections::{HashMap, VecDeque};
+use std::ffi::{c_char, c_int, CStr, CString};
+use std::time::{Duration, Instant};
+
+use once_cell::sync::Lazy;
+use parking_lot::{Mutex, RwLock};
+use serde::Serialize;
+
+use crate::bridge;
+use crate::metamod::types::{
+    edict_t, entvars_t, EngineFuncs, GlobalVars, FL_FAKECLIENT, MSG_ALL, PRINT_CHAT,
+};
+
+mod log;
+mod scoreboard;
+
+pub use log::{parse_log_line, LogEvent};
+pub use scoreboard::{on_log_line, reset_deaths, scoreboard, subscribe_scoreboard};
+
+/// HLDS hostname cvar
+const CVAR_HOSTNAME: &[u8] = b"hostname\0";
+
+/// Snapshot of the running server, refreshed on the game thread.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ServerInfo {
+    /// Server name (hostname cvar)
+    pub hostname: String,
+    /// Current map
+    pub map: String,
+    /// Game directory (e.g. `cstrike`)
+    pub gamedir: String,
+    /// Maximum number of player slots
+    pub max_players: i32,
+    /// Connected human players (native and web)
+    pub players: usize,
+    /// Connected bots
+    pub bots: usize,
+    /// Connected players using the WebRTC bridge
+    pub web_players: usize,
+}
+
+/// Latest published server snapshot
+static SERVER_INFO: Lazy<RwLock<ServerInfo>> = Lazy::new(|| RwLock::new(ServerInfo::default()));
+
+/// Connected client addresses keyed by entity index (from `ClientConnect`)
+static CLIENT_ADDRESSES: Lazy<Mutex<HashMap<c_int, String>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// Server commands waiting to run on the game thread
+static COMMAND_QUEUE: Lazy<Mutex<VecDeque<CString>>> = Lazy::new(|| Mutex::new(VecDeque::new()));
+
+/// Time of the last game frame (`StartFrame` heartbeat)
+static LAST_FRAME: Lazy<Mutex<Option<Instant>>> = Lazy::new(|| Mutex::new(None));
+
+/// Get the latest server snapshot.
+pub fn server_info() -> ServerInfo {
+    SERVER_INFO.read().clone()
+}
+
+/// Queue a server console command to run on the next game frame.
+///
+/// Fails if the command contains line breaks or null bytes, which would let it
+/// smuggle in additional commands.
+pub fn queue_server_command(command: &str) -> Result<(), &'static str> {
+    let command = command.trim();
+    if command.is_empty() {
+        return Err("empty command");
+    }
+    if command.contains(['\n', '\r']) {
+        return Err("command must be a single line");
+    }
+
+    let command =
+        CString::new(format!("{command}\n")).map_err(|_| "command contains a null byte")?;
+    COMMAND_QUEUE.lock().push_back(command);
+    Ok(())
+}
+
+/// Run queued server commands.
+///
+/// # Safety
+/// Must be called on the game thread with a valid engine function table.
+pub unsafe fn run_queued_commands(funcs: &EngineFuncs) {
+    let (Some(server_command), Some(server_execute)) =
+        (funcs.pfn_server_command, funcs.pfn_server_execute)
+    else {
+        return;
+    };
+
+    let commands: Vec<_> = COMMAND_QUEUE.lock().drain(..).collect();
+    if commands.is_empty() {
+        return;
+    }
+
+    for command in &commands {
+        server_command(command.as_ptr());
+    }
+    server_execute();
+}
+
+/// Record that a game frame started (game thread).
+pub fn record_frame() {
+    *LAST_FRAME.lock() = Some(Instant::now());
+}
+
+/// Time since the last game frame, or `None` if no frame has run yet.
+pub fn last_frame_age() -> Option<Duration> {
+    LAST_FRAME.lock().map(|at| at.elapsed())
+}
+
+/// Record a client connection (game thread).
+pub fn on_client_connect(index: c_int, address: String) {
+    CLIENT_ADDRESSES.lock().insert(index, address);
+}
+
+/// Record a client disconnection (game thread).
+pub fn on_client_disconnect(index: c_int) {
+    CLIENT_ADDRESSES.lock().remove(&index);
+}
+
+/// Collect the server state from the engine and publish it.
+///
+/// # Safety
+/// Must be called on the game thread with valid engine function and global pointers.
+pub unsafe fn refresh_server_info(funcs: &EngineFuncs, globals: &GlobalVars) {
+    let mut info = ServerInfo {
+        max_players: globals.max_clients,
+        ..ServerInfo::default()
+    };
+
+    if let Some(sz_from_index) = funcs.pfn_sz_from_index {
+        info.map = c_string(sz_from_index(globals.mapname));
+    }
+    if let Some(cvar_get_string) = funcs.pfn_cvar_get_string {
+        info.hostname = c_string(cvar_get_string(CVAR_HOSTNAME.as_ptr().cast()));
+    }
+    if let Some(get_game_dir) = funcs.pfn_get_game_dir {
+        let mut buf = [0 as c_char; 256];
+        get_game_dir(buf.as_mut_ptr());
+        info.gamedir = c_string(buf.as_ptr());
+    }
+
+    let players = connected_players(funcs, globals);
+    for player in &players {
+        if player.bot {
+            info.bots += 1;
+        } else {
+            info.players += 1;
+            if player.web {
+                info.web_players += 1;
+            }
+        }
+    }
+
+    scoreboard::refresh_scoreboard(funcs, &info.map, &players);
+    *SERVER_INFO.write() = info;
+}
+
+/// A player slot in use
+struct ConnectedPlayer {
+    /// Entity index
+    index: c_int,
+    edict: *mut edict_t,
+    vars: *mut entvars_t,
+    /// `FL_FAKECLIENT` is set
+    bot: bool,
+    /// Connected through the WebRTC bridge
+    web: bool,
+}
+
+/// Walk the player edicts `1..=max_clients`.
+///
+/// Bots created with `CREATE_FAKE_CLIENT` never pass `ClientConnect`, so the
+/// recorded addresses only tell which players are web clients.
+///
+/// # Safety
+/// Must be called on the game thread with valid engine function and global pointers.
+unsafe fn connected_players(funcs: &EngineFuncs, globals: &GlobalVars) -> Vec<ConnectedPlayer> {
+    let (Some(pent_of_ent_index), Some(get_vars_of_ent), Some(sz_from_index)) = (
+        funcs.pfn_pent_of_ent_index,
+        funcs.pfn_get_vars_of_ent,
+        funcs.pfn_sz_from_index,
+    ) else {
+        return Vec::new();
+    };
+
+    let addresses = CLIENT_ADDRESSES.lock();
+    let mut players = Vec::new();
+    for index in 1..=globals.max_clients {
+        let edict = pent_of_ent_index(index);
+        if edict.is_null() || (*edict).free != 0 {
+            continue;
+        }
+        let vars = get_vars_of_ent(edict);
+        if vars.is_null()
+            || (*vars).netname == 0
+            || c_string(sz_from_index((*vars).netname)).is_empty()
+        {
+            continue;
+        }
+
+        players.push(ConnectedPlayer {
+            index,
+            edict,
+            vars,
+            bot: (*vars).flags & FL_FAKECLIENT != 0,
+            web: addresses
+                .get(&index)
+                .is_some_and(|address| bridge::is_bridge_addr(address)),
+        });
+    }
+    players
+}
+
+/// Print chat lines to every player.
+///
+/// Uses the game's `SayText` user message when its id is known, otherwise
+/// `pfnClientPrintf` to each connected human player.
+///
+/// # Safety
+/// Must be called on the game thread with a valid engine function table.
+pub unsafe fn print_chat(funcs: &EngineFuncs, say_text: Option<c_int>, lines: &[String]) {
+    for line in lines {
+        let Ok(text) = CString::new(format!("{line}\n")) else {
+            continue;
+        };
+
+        if let (
+            Some(msg_type),
+            Some(message_begin),
+            Some(write_byte),
+            Some(write_string),
+            Some(message_end),
+        ) = (
+            say_text,
+            funcs.pfn_message_begin,
+            funcs.pfn_write_byte,
+            funcs.pfn_write_string,
+            funcs.pfn_message_end,
+        ) {
+            message_begin(MSG_ALL, msg_type, std::ptr::null(), std::ptr::null_mut());
+            // Sender entity index; 0 is the server
+            write_byte(0);
+            write_string(text.as_ptr());
+            message_end();
+            continue;
+        }
+
+        let (Some(pent_of_ent_index), Some(client_printf)) =
+            (funcs.pfn_pent_of_ent_index, funcs.pfn_client_printf)
+        else {
+            return;
+        };
+        let clients: Vec<c_int> = CLIENT_ADDRESSES.lock().keys().copied().collect();
+        for index in clients {
+            if is_fake_client(funcs, index) {
+                continue;
+            }
+            let edict = pent_of_ent_index(index);
+            if !edict.is_null() {
+                client_printf(edict, PRINT_CHAT, text.as_ptr());
+            }
+        }
+    }
+}
+
+/// Check the `FL_FAKECLIENT` flag of a player entity.
+unsafe fn is_fake_client(funcs: &EngineFuncs, index: c_int) -> bool {
+    let (Some(pent_of_ent_index), Some(get_vars_of_ent)) =
+        (funcs.pfn_pent_of_ent_index, funcs.pfn_get_vars_of_ent)
+    else {
+        return false;
+    };
+
+    let edict = pent_of_ent_index(index);
+    if edict.is_null() {
+        return false;
+    }
+
+    let vars = get_vars_of_ent(edict);
+    !vars.is_null() && (*vars).flags & FL_FAKECLIENT != 0
+}
+
+/// Copy a C string from the engine, treating null as empty.
+///
+/// # Safety
+/// `ptr` must be null or point to a valid null-terminated string.
+pub unsafe fn c_string(ptr: *const c_char) -> String {
+    if ptr.is_null() {
+        String::new()
+    } else {
+        CStr::from_ptr(ptr).to_string_lossy().into_owned()
+    }
+}
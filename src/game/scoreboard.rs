@@ -0,0 +1,247 @@
+//! Live scoreboard of the connected players.
+//!
+//! Read from the player entities on the game thread every second. Deaths and
+//! team names are not part of the entity variables, so they are taken from
+//! the HL log lines (`"Name<userid><authid><team>" killed ...`).
+
+use std::collections::HashMap;
+use std::ffi::c_int;
+use std::ptr;
+use std::sync::Arc;
+
+use once_cell::sync::Lazy;
+use parking_lot::Mutex;
+use serde::Serialize;
+use tokio::sync::watch;
+
+use super::log::{parse_log_line, LogEvent, LogPlayer};
+use super::{c_string, ConnectedPlayer};
+use crate::metamod::types::EngineFuncs;
+
+/// Info key of the player name
+const INFO_KEY_NAME: &[u8] = b"name\0";
+
+/// Latest published scoreboard
+static SCOREBOARD: Lazy<watch::Sender<Arc<Scoreboard>>> =
+    Lazy::new(|| watch::channel(Arc::new(Scoreboard::default())).0);
+
+/// Deaths and teams seen in log lines, keyed by user id
+static LOG_STATS: Lazy<Mutex<HashMap<c_int, LogStats>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+#[derive(Debug, Default)]
+struct LogStats {
+    deaths: u32,
+    team: Option<String>,
+}
+
+/// Scoreboard snapshot
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct Scoreboard {
+    pub map: String,
+    /// Players sorted by frags, then deaths
+    pub players: Vec<PlayerScore>,
+}
+
+/// One player's row
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PlayerScore {
+    /// Entity index
+    pub slot: c_int,
+    pub name: String,
+    pub frags: i32,
+    /// Deaths since the map started (from log lines)
+    pub deaths: u32,
+    /// Team name from log lines, or the entity team number
+    pub team: Option<String>,
+    /// Ping in ms (`pfnGetPlayerStats`)
+    pub ping: Option<i32>,
+    /// Packet loss in percent (`pfnGetPlayerStats`)
+    pub loss: Option<i32>,
+    /// Connected through the WebRTC bridge
+    pub web: bool,
+    pub bot: bool,
+}
+
+/// Get the latest scoreboard.
+pub fn scoreboard() -> Arc<Scoreboard> {
+    SCOREBOARD.borrow().clone()
+}
+
+/// Subscribe to scoreboard changes.
+pub fn subscribe_scoreboard() -> watch::Receiver<Arc<Scoreboard>> {
+    SCOREBOARD.subscribe()
+}
+
+/// Forget the deaths of the previous map (game thread).
+pub fn reset_deaths() {
+    for stats in LOG_STATS.lock().values_mut() {
+        stats.deaths = 0;
+    }
+}
+
+/// Update deaths and teams from an HL log line.
+pub fn on_log_line(line: &str) {
+    let Some(event) = parse_log_line(line) else {
+        return;
+    };
+
+    let mut log_stats = LOG_STATS.lock();
+    match event {
+        LogEvent::Kill { killer, victim, .. } => {
+            note_team(&mut log_stats, &killer);
+            note_team(&mut log_stats, &victim);
+            log_stats.entry(victim.userid).or_default().deaths += 1;
+        }
+        LogEvent::Suicide { player, .. } => {
+            note_team(&mut log_stats, &player);
+            log_stats.entry(player.userid).or_default().deaths += 1;
+        }
+        // The player's own team is the old one, the new one comes last
+        LogEvent::JoinTeam { player, team } => {
+            log_stats.entry(player.userid).or_default().team = Some(team.to_string());
+        }
+    }
+}
+
+/// Remember the team a player is logged with.
+fn note_team(log_stats: &mut HashMap<c_int, LogStats>, player: &LogPlayer) {
+    // Spectators and unassigned players log an empty team
+    if !player.team.is_empty() {
+        log_stats.entry(player.userid).or_default().team = Some(player.team.to_string());
+    }
+}
+
+/// Collect the player rows and publish a new scoreboard.
+///
+/// # Safety
+/// Must be called on the game thread with a valid engine function table.
+pub(super) unsafe fn refresh_scoreboard(
+    funcs: &EngineFuncs,
+    map: &str,
+    connected: &[ConnectedPlayer],
+) {
+    let mut players = Vec::with_capacity(connected.len());
+    let mut userids = Vec::with_capacity(connected.len());
+
+    for player in connected {
+        let (edict, vars) = (player.edict, player.vars);
+
+        let mut name = String::new();
+        if let (Some(get_info_key_buffer), Some(info_key_value)) =
+            (funcs.pfn_get_info_key_buffer, funcs.pfn_info_key_value)
+        {
+            let buffer = get_info_key_buffer(edict);
+            if !buffer.is_null() {
+                name = c_string(info_key_value(buffer, INFO_KEY_NAME.as_ptr().cast()));
+            }
+        }
+        if name.is_empty() {
+            if let Some(sz_from_index) = funcs.pfn_sz_from_index {
+                name = c_string(sz_from_index((*vars).netname));
+            }
+        }
+
+        let bot = player.bot;
+        let (mut ping, mut loss) = (None, None);
+        if let Some(get_player_stats) = funcs.pfn_get_player_stats {
+            if !bot {
+                let (mut p, mut l) = (0, 0);
+                get_player_stats(edict, ptr::addr_of_mut!(p), ptr::addr_of_mut!(l));
+                (ping, loss) = (Some(p), Some(l));
+            }
+        }
+
+        let userid = funcs
+            .pfn_get_player_userid
+            .map(|get_userid| get_userid(edict));
+        userids.extend(userid);
+        let (deaths, log_team) = userid
+            .and_then(|userid| {
+                LOG_STATS
+                    .lock()
+                    .get(&userid)
+                    .map(|stats| (stats.deaths, stats.team.clone()))
+            })
+            .unwrap_or_default();
+        let team = log_team.or_else(|| ((*vars).team != 0).then(|| (*vars).team.to_string()));
+
+        players.push(PlayerScore {
+            slot: player.index,
+            name,
+            frags: whole((*vars).frags),
+            deaths,
+            team,
+            ping,
+            loss,
+            web: player.web,
+            bot,
+        });
+    }
+
+    // User ids are not reused, so stats of departed players can go
+    if funcs.pfn_get_player_userid.is_some() {
+        LOG_STATS
+            .lock()
+            .retain(|userid, _| userids.contains(userid));
+    }
+
+    players.sort_by(|a, b| b.frags.cmp(&a.frags).then(a.deaths.cmp(&b.deaths)));
+
+    let scoreboard = Scoreboard {
+        map: map.to_string(),
+        players,
+    };
+    // Only wake subscribers on changes
+    SCOREBOARD.send_if_modified(|current| {
+        if **current == scoreboard {
+            false
+        } else {
+            *current = Arc::new(scoreboard);
+            true
+        }
+    });
+}
+
+/// Convert a float counter of the entity variables.
+#[allow(clippy::cast_possible_truncation)]
+fn whole(value: f32) -> i32 {
+    // Saturating; frags are whole numbers far from the limits
+    value.round() as i32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Deaths and team of a user id (each test uses its own ids).
+    fn stats(userid: c_int) -> (u32, Option<String>) {
+        LOG_STATS
+            .lock()
+            .get(&userid)
+            .map_or((0, None), |stats| (stats.deaths, stats.team.clone()))
+    }
+
+    #[test]
+    fn kills_suicides_and_team_joins() {
+        on_log_line("\"Player<101><STEAM_0:1:1><>\" joined team \"CT\"");
+        on_log_line(
+            "\"Player<101><STEAM_0:1:1><CT>\" killed \"Bot<102><BOT><TERRORIST>\" with \"ak47\"",
+        );
+        on_log_line("\"Bot<102><BOT><TERRORIST>\" committed suicide with \"world\"");
+
+        assert_eq!(stats(101), (0, Some("CT".to_string())));
+        assert_eq!(stats(102), (2, Some("TERRORIST".to_string())));
+    }
+
+    #[test]
+    fn chat_lines_do_not_change_stats() {
+        on_log_line(
+            "\"Evil<111><STEAM_0:0:1><CT>\" say \" killed \"Victim<112><BOT><TERRORIST>\" with \"awp\"",
+        );
+        on_log_line("\"Evil<111><STEAM_0:0:1><CT>\" say_team \"\"Victim<112><BOT><SPECTATOR>\" joined team \"SPECTATOR\"");
+        on_log_line("\"Evil<111><STEAM_0:0:1><CT>\" say \"\"Victim<112><BOT><CT>\" committed suicide with \"world\"");
+
+        assert_eq!(stats(111), (0, None));
+        assert_eq!(stats(112), (0, None));
+    }
+}
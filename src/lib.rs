@@ -10,12 +10,20 @@
 #![allow(clippy::module_name_repetitions)]
 #![allow(clippy::must_use_candidate)]
 
+mod a2s;
 mod bridge;
+mod chat;
 mod config;
+mod console;
+mod events;
+mod game;
 mod metamod;
+mod metrics;
 mod plugin;
 mod runtime;
 mod server;
+mod session;
+mod turn;
 mod webrtc;
 
 // Re-export the Metamod exports for the DLL
@@ -4,8 +4,12 @@
 
 use std::ffi::{c_char, c_int};
 use std::ptr;
+use std::sync::atomic::{AtomicPtr, Ordering};
 
 use super::types::*;
+use crate::console;
+use crate::events;
+use crate::game;
 use crate::plugin::PLUGIN;
 
 // =============================================================================
@@ -92,10 +96,11 @@ pub unsafe extern "C" fn Meta_Attach(
     (*function_table).pfn_get_entity_api2_post = None;
     (*function_table).pfn_get_new_dll_functions = None;
     (*function_table).pfn_get_new_dll_functions_post = None;
-    (*function_table).pfn_get_engine_functions = None;
+    (*function_table).pfn_get_engine_functions = Some(get_engine_functions);
     (*function_table).pfn_get_engine_functions_post = None;
 
     // Store globals (server will start on first map load in on_server_activate)
+    META_GLOBALS.store(meta_globals, Ordering::Release);
     let mut plugin = PLUGIN.lock();
     plugin.set_meta_globals(meta_globals);
     plugin.set_gamedll_funcs(gamedll_funcs);
@@ -145,17 +150,17 @@ static mut G_DLL_FUNCS: DllFunctions = DllFunctions {
     pfn_save_global_state: None,
     pfn_restore_global_state: None,
     pfn_reset_global_state: None,
-    pfn_client_connect: None,
-    pfn_client_disconnect: None,
+    pfn_client_connect: Some(client_connect),
+    pfn_client_disconnect: Some(client_disconnect),
     pfn_client_kill: None,
     pfn_client_put_in_server: None,
-    pfn_client_command: None,
+    pfn_client_command: Some(client_command),
     pfn_client_user_info_changed: None,
     pfn_server_activate: Some(server_activate),
     pfn_server_deactivate: Some(server_deactivate),
     pfn_player_pre_think: None,
     pfn_player_post_think: None,
-    pfn_start_frame: None,
+    pfn_start_frame: Some(start_frame),
     pfn_params_new_level: None,
     pfn_params_change_level: None,
     pfn_get_game_description: None,
@@ -201,6 +206,34 @@ pub unsafe extern "C" fn get_entity_api2(
     1 // TRUE - success
 }
 
+/// Get engine API hooks.
+///
+/// # Safety
+/// Called by Metamod with valid pointers.
+#[no_mangle]
+pub unsafe extern "C" fn get_engine_functions(
+    func_table: *mut EngineFuncs,
+    interface_version: *mut c_int,
+) -> c_int {
+    if func_table.is_null() || interface_version.is_null() {
+        return 0;
+    }
+
+    // Every entry is an optional function pointer, so all-zero hooks nothing
+    ptr::write_bytes(func_table, 0, 1);
+    (*func_table).pfn_server_print = Some(server_print);
+    // The hook reads the variadic arguments as fixed words, see `alert_message`
+    #[cfg(target_arch = "x86")]
+    {
+        (*func_table).pfn_alert_message = Some(std::mem::transmute::<
+            unsafe extern "C" fn(c_int, *const c_char, AlertArgs),
+            unsafe extern "C" fn(c_int, *const c_char, ...),
+        >(alert_message));
+    }
+
+    1 // TRUE - success
+}
+
 // =============================================================================
 // Hook Implementations
 // =============================================================================
@@ -223,3 +256,138 @@ unsafe extern "C" fn server_activate(
 unsafe extern "C" fn server_deactivate() {
     PLUGIN.lock().on_server_deactivate();
 }
+
+/// Called when a client connects.
+unsafe extern "C" fn client_connect(
+    edict: *mut edict_t,
+    name: *const c_char,
+    address: *const c_char,
+    _reject_reason: *mut c_char,
+) -> c_int {
+    let mut plugin = PLUGIN.lock();
+    plugin.on_client_connect(edict, game::c_string(name), game::c_string(address));
+    plugin.set_meta_result(MetaResult::Ignored);
+
+    1 // TRUE - allow connection
+}
+
+/// Called when a client disconnects.
+unsafe extern "C" fn client_disconnect(edict: *mut edict_t) {
+    let mut plugin = PLUGIN.lock();
+    plugin.on_client_disconnect(edict);
+    plugin.set_meta_result(MetaResult::Ignored);
+}
+
+/// Called when a client sends a command (`say`, `say_team`, ...).
+unsafe extern "C" fn client_command(edict: *mut edict_t) {
+    let mut plugin = PLUGIN.lock();
+    plugin.on_client_command(edict);
+    plugin.set_meta_result(MetaResult::Ignored);
+}
+
+/// Called at the start of every server frame.
+unsafe extern "C" fn start_frame() {
+    let engine_funcs = {
+        let mut plugin = PLUGIN.lock();
+        plugin.on_start_frame();
+        plugin.set_meta_result(MetaResult::Ignored);
+        plugin.engine_funcs()
+    };
+
+    // Queued commands may call back into the plugin (console commands, print hooks)
+    if !engine_funcs.is_null() {
+        game::run_queued_commands(&*engine_funcs);
+    }
+}
+
+// =============================================================================
+// Engine Hook Implementations
+// =============================================================================
+
+/// Meta globals for the engine hooks.
+///
+/// Engine hooks can run while `PLUGIN` is locked (e.g. a game DLL printing
+/// from a command we execute), so they must not lock it.
+static META_GLOBALS: AtomicPtr<MetaGlobals> = AtomicPtr::new(ptr::null_mut());
+
+/// Set the Metamod result without locking `PLUGIN`.
+unsafe fn set_engine_hook_result(result: MetaResult) {
+    let globals = META_GLOBALS.load(Ordering::Acquire);
+    if !globals.is_null() {
+        (*globals).mres = result;
+    }
+}
+
+/// Called when the game DLL prints to the server console.
+unsafe extern "C" fn server_print(msg: *const c_char) {
+    if console::has_subscribers() {
+        console::publish_print(game::c_string(msg));
+    }
+    set_engine_hook_result(MetaResult::Ignored);
+}
+
+/// Variadic arguments of `pfnAlertMessage`, read as plain stack words.
+///
+/// Stable Rust cannot define variadic functions. With cdecl (the 32-bit HLDS
+/// ABI) the caller owns the stack, so reading a fixed number of words and
+/// passing them on to `snprintf` reproduces the original call for up to this
+/// many words of arguments. Other ABIs pass variadic arguments in registers,
+/// so the hook is only installed on 32-bit x86.
+#[cfg(target_arch = "x86")]
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct AlertArgs([usize; 8]);
+
+/// Size of the formatting buffer, as in the engine's `AlertMessage`
+#[cfg(target_arch = "x86")]
+const ALERT_BUFFER_SIZE: usize = 1024;
+
+#[cfg(target_arch = "x86")]
+extern "C" {
+    fn snprintf(buf: *mut c_char, len: usize, format: *const c_char, ...) -> c_int;
+}
+
+/// Called when the game DLL sends an alert (developer messages, log lines).
+#[cfg(target_arch = "x86")]
+unsafe extern "C" fn alert_message(alert_type: c_int, format: *const c_char, args: AlertArgs) {
+    if alert_wanted(alert_type) && !format.is_null() {
+        let mut buf = [0 as c_char; ALERT_BUFFER_SIZE];
+        let [a0, a1, a2, a3, a4, a5, a6, a7] = args.0;
+        snprintf(
+            buf.as_mut_ptr(),
+            buf.len(),
+            format,
+            a0,
+            a1,
+            a2,
+            a3,
+            a4,
+            a5,
+            a6,
+            a7,
+        );
+        on_alert(alert_type, game::c_string(buf.as_ptr()));
+    }
+    set_engine_hook_result(MetaResult::Ignored);
+}
+
+/// Check whether an alert has any consumer, before formatting it.
+#[cfg_attr(not(target_arch = "x86"), allow(dead_code))]
+fn alert_wanted(alert_type: c_int) -> bool {
+    // Log lines also feed the scoreboard, so they are always formatted
+    alert_type == AT_LOGGED || console::has_subscribers()
+}
+
+/// Pass a formatted alert on to the scoreboard, event feed and console.
+#[cfg_attr(not(target_arch = "x86"), allow(dead_code))]
+fn on_alert(alert_type: c_int, text: String) {
+    if alert_type == AT_LOGGED {
+        game::on_log_line(&text);
+        if events::has_subscribers() {
+            events::publish_log_line(&text);
+        }
+    }
+    if console::has_subscribers() {
+        console::publish_alert(alert_type, text);
+    }
+}
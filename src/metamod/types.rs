@@ -11,20 +11,156 @@ use std::ffi::{c_char, c_float, c_int, c_uchar, c_void};
 // Forward declarations for opaque engine types
 // =============================================================================
 
-/// Opaque edict structure (entity dictionary)
+/// Edict structure (entity dictionary), opaque past the leading `free` flag
 #[repr(C)]
 pub struct edict_s {
+    /// Nonzero while the edict is not in use (`qboolean`)
+    pub free: c_int,
     _opaque: [u8; 0],
 }
 pub type edict_t = edict_s;
 
-/// Opaque entvars structure (entity variables)
+/// Entity variables (progdefs.h)
 #[repr(C)]
 pub struct entvars_s {
-    _opaque: [u8; 0],
+    pub classname: c_int, // string_t
+    pub globalname: c_int,
+    pub origin: [c_float; 3],
+    pub oldorigin: [c_float; 3],
+    pub velocity: [c_float; 3],
+    pub basevelocity: [c_float; 3],
+    pub clbasevelocity: [c_float; 3],
+    pub movedir: [c_float; 3],
+    pub angles: [c_float; 3],
+    pub avelocity: [c_float; 3],
+    pub punchangle: [c_float; 3],
+    pub v_angle: [c_float; 3],
+    pub endpos: [c_float; 3],
+    pub startpos: [c_float; 3],
+    pub impacttime: c_float,
+    pub starttime: c_float,
+    pub fixangle: c_int,
+    pub idealpitch: c_float,
+    pub pitch_speed: c_float,
+    pub ideal_yaw: c_float,
+    pub yaw_speed: c_float,
+    pub modelindex: c_int,
+    pub model: c_int,
+    pub viewmodel: c_int,
+    pub weaponmodel: c_int,
+    pub absmin: [c_float; 3],
+    pub absmax: [c_float; 3],
+    pub mins: [c_float; 3],
+    pub maxs: [c_float; 3],
+    pub size: [c_float; 3],
+    pub ltime: c_float,
+    pub nextthink: c_float,
+    pub movetype: c_int,
+    pub solid: c_int,
+    pub skin: c_int,
+    pub body: c_int,
+    pub effects: c_int,
+    pub gravity: c_float,
+    pub friction: c_float,
+    pub light_level: c_int,
+    pub sequence: c_int,
+    pub gaitsequence: c_int,
+    pub frame: c_float,
+    pub animtime: c_float,
+    pub framerate: c_float,
+    pub controller: [c_uchar; 4],
+    pub blending: [c_uchar; 2],
+    pub scale: c_float,
+    pub rendermode: c_int,
+    pub renderamt: c_float,
+    pub rendercolor: [c_float; 3],
+    pub renderfx: c_int,
+    pub health: c_float,
+    pub frags: c_float,
+    pub weapons: c_int,
+    pub takedamage: c_float,
+    pub deadflag: c_int,
+    pub view_ofs: [c_float; 3],
+    pub button: c_int,
+    pub impulse: c_int,
+    pub chain: *mut edict_t,
+    pub dmg_inflictor: *mut edict_t,
+    pub enemy: *mut edict_t,
+    pub aiment: *mut edict_t,
+    pub owner: *mut edict_t,
+    pub groundentity: *mut edict_t,
+    pub spawnflags: c_int,
+    pub flags: c_int,
+    pub colormap: c_int,
+    pub team: c_int,
+    pub max_health: c_float,
+    pub teleport_time: c_float,
+    pub armortype: c_float,
+    pub armorvalue: c_float,
+    pub waterlevel: c_int,
+    pub watertype: c_int,
+    pub target: c_int,
+    pub targetname: c_int,
+    pub netname: c_int,
+    pub message: c_int,
+    pub dmg_take: c_float,
+    pub dmg_save: c_float,
+    pub dmg: c_float,
+    pub dmgtime: c_float,
+    pub noise: c_int,
+    pub noise1: c_int,
+    pub noise2: c_int,
+    pub noise3: c_int,
+    pub speed: c_float,
+    pub air_finished: c_float,
+    pub pain_finished: c_float,
+    pub radsuit_finished: c_float,
+    pub p_containing_entity: *mut edict_t,
+    pub playerclass: c_int,
+    pub maxspeed: c_float,
+    pub fov: c_float,
+    pub weaponanim: c_int,
+    pub pushmsec: c_int,
+    pub b_in_duck: c_int,
+    pub fl_time_step_sound: c_int,
+    pub fl_swim_time: c_int,
+    pub fl_duck_time: c_int,
+    pub i_step_left: c_int,
+    pub fl_fall_velocity: c_float,
+    pub gamestate: c_int,
+    pub oldbuttons: c_int,
+    pub groupinfo: c_int,
+    pub iuser1: c_int,
+    pub iuser2: c_int,
+    pub iuser3: c_int,
+    pub iuser4: c_int,
+    pub fuser1: c_float,
+    pub fuser2: c_float,
+    pub fuser3: c_float,
+    pub fuser4: c_float,
+    pub vuser1: [c_float; 3],
+    pub vuser2: [c_float; 3],
+    pub vuser3: [c_float; 3],
+    pub vuser4: [c_float; 3],
+    pub euser1: *mut edict_t,
+    pub euser2: *mut edict_t,
+    pub euser3: *mut edict_t,
+    pub euser4: *mut edict_t,
 }
 pub type entvars_t = entvars_s;
 
+/// Entity flag: bot (fake client)
+pub const FL_FAKECLIENT: c_int = 1 << 13;
+
+/// Alert type of HL log lines (`at_logged`)
+pub const AT_LOGGED: c_int = 5;
+
+/// `pfnClientPrintf` destination: chat area (`print_chat`)
+pub const PRINT_CHAT: c_int = 2;
+
+/// `pfnMessageBegin` destination: all clients, reliable (`MSG_ALL`)
+pub const MSG_ALL: c_int = 2;
+
 /// Opaque cvar structure
 #[repr(C)]
 pub struct cvar_s {
@@ -36,6 +172,11 @@ pub struct cvar_s {
 }
 pub type cvar_t = cvar_s;
 
+/// Cvar flag: defined by an external DLL
+pub const FCVAR_EXTDLL: c_int = 1 << 3;
+/// Cvar flag: value is hidden from clients and server queries
+pub const FCVAR_PROTECTED: c_int = 1 << 5;
+
 // =============================================================================
 // Metamod Plugin Info
 // =============================================================================
@@ -426,6 +567,10 @@ pub struct EngineFuncs {
     pub pfn_set_group_mask: Option<unsafe extern "C" fn(c_int, c_int)>,
     pub pfn_engine_stub1: Option<unsafe extern "C" fn(c_int, *const c_char) -> c_int>,
     pub pfn_engine_stub2: Option<unsafe extern "C" fn()>,
+    pub pfn_force_unmodified:
+        Option<unsafe extern "C" fn(c_int, *mut c_float, *mut c_float, *const c_char)>,
+    pub pfn_get_player_stats: Option<unsafe extern "C" fn(*const edict_t, *mut c_int, *mut c_int)>,
+    pub pfn_add_server_command: Option<unsafe extern "C" fn(*const c_char, unsafe extern "C" fn())>,
     pub pfn_voice_get_client_listening: Option<unsafe extern "C" fn(c_int, c_int) -> c_int>,
     pub pfn_voice_set_client_listening: Option<unsafe extern "C" fn(c_int, c_int, c_int) -> c_int>,
     pub pfn_get_player_auth_id: Option<unsafe extern "C" fn(*mut edict_t) -> *const c_char>,
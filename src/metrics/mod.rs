@@ -0,0 +1,298 @@
+//! Prometheus metrics.
+//!
+//! Process-wide counters and gauges updated by the HTTP server, signaling and
+//! bridges, rendered in the Prometheus text exposition format by `GET /metrics`.
+
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::sync::atomic::{AtomicI64, AtomicU64, Ordering};
+use std::time::Duration;
+
+use once_cell::sync::Lazy;
+use parking_lot::Mutex;
+use webrtc::ice_transport::ice_connection_state::RTCIceConnectionState;
+
+/// Monotonically increasing counter
+pub struct Counter(AtomicU64);
+
+impl Counter {
+    const fn new() -> Self {
+        Self(AtomicU64::new(0))
+    }
+
+    pub fn inc(&self) {
+        self.add(1);
+    }
+
+    pub fn add(&self, n: u64) {
+        self.0.fetch_add(n, Ordering::Relaxed);
+    }
+
+    pub fn get(&self) -> u64 {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// Value that can go up and down
+pub struct Gauge(AtomicI64);
+
+impl Gauge {
+    const fn new() -> Self {
+        Self(AtomicI64::new(0))
+    }
+
+    pub fn inc(&self) {
+        self.0.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn dec(&self) {
+        self.0.fetch_sub(1, Ordering::Relaxed);
+    }
+
+    pub fn set(&self, value: i64) {
+        self.0.store(value, Ordering::Relaxed);
+    }
+
+    pub fn get(&self) -> i64 {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// HTTP response bytes written (headers and body)
+pub static HTTP_BYTES_SENT: Counter = Counter::new();
+/// Open signaling WebSocket sessions
+pub static WS_SESSIONS: Gauge = Gauge::new();
+/// Open WebTransport sessions
+pub static WEBTRANSPORT_SESSIONS: Gauge = Gauge::new();
+/// Active UDP bridges
+pub static BRIDGES: Gauge = Gauge::new();
+/// Packets forwarded from browsers to the game server
+pub static PACKETS_TO_GAME: Counter = Counter::new();
+/// Bytes forwarded from browsers to the game server
+pub static BYTES_TO_GAME: Counter = Counter::new();
+/// Packets forwarded from the game server to browsers
+pub static PACKETS_TO_CLIENT: Counter = Counter::new();
+/// Bytes forwarded from the game server to browsers
+pub static BYTES_TO_CLIENT: Counter = Counter::new();
+/// Failed UDP sends to the game server
+pub static SEND_ERRORS_TO_GAME: Counter = Counter::new();
+/// Failed data channel sends to browsers
+pub static SEND_ERRORS_TO_CLIENT: Counter = Counter::new();
+/// Packets discarded by bridges without sending
+pub static DROPPED_PACKETS: Counter = Counter::new();
+/// Files listed in resources.jsonl
+pub static RESOURCE_FILES: Gauge = Gauge::new();
+/// Size of resources.jsonl in bytes
+pub static RESOURCE_INDEX_BYTES: Gauge = Gauge::new();
+
+/// Offers sent by signaling sessions
+static OFFERS: Counter = Counter::new();
+/// Total time from WebSocket connection to offer, in microseconds
+static OFFER_MICROS: Counter = Counter::new();
+
+/// HTTP requests by (route, status code)
+static HTTP_REQUESTS: Lazy<Mutex<BTreeMap<(&'static str, u16), u64>>> =
+    Lazy::new(|| Mutex::new(BTreeMap::new()));
+
+/// Peer connections by ICE connection state
+static PEERS_BY_ICE_STATE: Lazy<Mutex<BTreeMap<&'static str, i64>>> =
+    Lazy::new(|| Mutex::new(BTreeMap::new()));
+
+/// Count a completed HTTP request.
+///
+/// `route` must come from a fixed set of labels to keep cardinality bounded.
+pub fn record_http_request(route: &'static str, status: u16) {
+    *HTTP_REQUESTS.lock().entry((route, status)).or_insert(0) += 1;
+}
+
+/// Keeps one peer connection counted under its current ICE state.
+///
+/// Closed peers are not counted; dropping the tracker removes the peer.
+pub struct IceStateTracker {
+    current: Mutex<Option<&'static str>>,
+}
+
+impl IceStateTracker {
+    pub fn new() -> Self {
+        Self {
+            current: Mutex::new(None),
+        }
+    }
+
+    /// Move the peer to a new ICE state.
+    pub fn set(&self, state: RTCIceConnectionState) {
+        let label = match state {
+            RTCIceConnectionState::New => Some("new"),
+            RTCIceConnectionState::Checking => Some("checking"),
+            RTCIceConnectionState::Connected => Some("connected"),
+            RTCIceConnectionState::Completed => Some("completed"),
+            RTCIceConnectionState::Disconnected => Some("disconnected"),
+            RTCIceConnectionState::Failed => Some("failed"),
+            RTCIceConnectionState::Closed | RTCIceConnectionState::Unspecified => None,
+        };
+
+        let mut current = self.current.lock();
+        let mut peers = PEERS_BY_ICE_STATE.lock();
+        if let Some(old) = current.take() {
+            *peers.entry(old).or_insert(0) -= 1;
+        }
+        if let Some(new) = label {
+            *peers.entry(new).or_insert(0) += 1;
+        }
+        *current = label;
+    }
+}
+
+impl Default for IceStateTracker {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Drop for IceStateTracker {
+    fn drop(&mut self) {
+        if let Some(old) = self.current.get_mut().take() {
+            *PEERS_BY_ICE_STATE.lock().entry(old).or_insert(0) -= 1;
+        }
+    }
+}
+
+/// Render all metrics in the Prometheus text format.
+pub fn render() -> String {
+    let mut out = String::new();
+
+    header(
+        &mut out,
+        "webxash_http_requests_total",
+        "counter",
+        "HTTP requests by route and status.",
+    );
+    for ((route, status), count) in HTTP_REQUESTS.lock().iter() {
+        let _ = writeln!(
+            out,
+            "webxash_http_requests_total{{route=\"{route}\",status=\"{status}\"}} {count}"
+        );
+    }
+
+    header(
+        &mut out,
+        "webxash_peer_connections",
+        "gauge",
+        "WebRTC peer connections by ICE connection state.",
+    );
+    for (state, count) in PEERS_BY_ICE_STATE.lock().iter() {
+        let _ = writeln!(out, "webxash_peer_connections{{state=\"{state}\"}} {count}");
+    }
+
+    by_direction(
+        &mut out,
+        "webxash_bridge_packets_total",
+        "Packets forwarded by bridges.",
+        &PACKETS_TO_GAME,
+        &PACKETS_TO_CLIENT,
+    );
+    by_direction(
+        &mut out,
+        "webxash_bridge_bytes_total",
+        "Bytes forwarded by bridges.",
+        &BYTES_TO_GAME,
+        &BYTES_TO_CLIENT,
+    );
+    by_direction(
+        &mut out,
+        "webxash_bridge_send_errors_total",
+        "Failed packet sends by bridges.",
+        &SEND_ERRORS_TO_GAME,
+        &SEND_ERRORS_TO_CLIENT,
+    );
+
+    let simple: [(&str, &str, &str, i128); 7] = [
+        (
+            "webxash_http_sent_bytes_total",
+            "counter",
+            "HTTP response bytes sent.",
+            HTTP_BYTES_SENT.get().into(),
+        ),
+        (
+            "webxash_bridge_dropped_packets_total",
+            "counter",
+            "Packets discarded by bridges because the browser was not keeping up.",
+            DROPPED_PACKETS.get().into(),
+        ),
+        (
+            "webxash_websocket_sessions",
+            "gauge",
+            "Open signaling WebSocket sessions.",
+            WS_SESSIONS.get().into(),
+        ),
+        (
+            "webxash_webtransport_sessions",
+            "gauge",
+            "Open WebTransport sessions.",
+            WEBTRANSPORT_SESSIONS.get().into(),
+        ),
+        (
+            "webxash_bridges",
+            "gauge",
+            "Active UDP bridges.",
+            BRIDGES.get().into(),
+        ),
+        (
+            "webxash_resource_files",
+            "gauge",
+            "Files listed in resources.jsonl.",
+            RESOURCE_FILES.get().into(),
+        ),
+        (
+            "webxash_resource_index_bytes",
+            "gauge",
+            "Size of resources.jsonl in bytes.",
+            RESOURCE_INDEX_BYTES.get().into(),
+        ),
+    ];
+    for (name, kind, help, value) in simple {
+        header(&mut out, name, kind, help);
+        let _ = writeln!(out, "{name} {value}");
+    }
+
+    offer_summary(&mut out);
+
+    out
+}
+
+/// Record the time a signaling session took to send its offer.
+pub fn observe_offer(elapsed: Duration) {
+    OFFERS.inc();
+    OFFER_MICROS.add(u64::try_from(elapsed.as_micros()).unwrap_or(u64::MAX));
+}
+
+/// Write the time-to-offer summary.
+fn offer_summary(out: &mut String) {
+    let name = "webxash_signaling_offer_seconds";
+    header(
+        out,
+        name,
+        "summary",
+        "Time from signaling WebSocket connection to the offer being sent.",
+    );
+    let micros = OFFER_MICROS.get();
+    let _ = writeln!(
+        out,
+        "{name}_sum {}.{:06}",
+        micros / 1_000_000,
+        micros % 1_000_000
+    );
+    let _ = writeln!(out, "{name}_count {}", OFFERS.get());
+}
+
+/// Write a counter split by forwarding direction.
+fn by_direction(out: &mut String, name: &str, help: &str, to_game: &Counter, to_client: &Counter) {
+    header(out, name, "counter", help);
+    let _ = writeln!(out, "{name}{{direction=\"to_game\"}} {}", to_game.get());
+    let _ = writeln!(out, "{name}{{direction=\"to_client\"}} {}", to_client.get());
+}
+
+fn header(out: &mut String, name: &str, kind: &str, help: &str) {
+    let _ = writeln!(out, "# HELP {name} {help}");
+    let _ = writeln!(out, "# TYPE {name} {kind}");
+}
@@ -4,15 +4,134 @@
 
 use once_cell::sync::Lazy;
 use parking_lot::Mutex;
+use std::ffi::c_int;
 use std::ptr;
-
-use crate::config::PluginConfig;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::bridge;
+use crate::chat;
+use crate::config::{
+    is_turn_url, normalize_base_path, parse_ice_server_list, parse_origin_list, parse_rcon_users,
+    parse_server_list, ChannelReliability, IcePorts, IpCidr, PluginConfig,
+};
+use crate::events::{self, EventKind};
+use crate::game;
+use crate::metamod::exports::PLUGIN_INFO;
 use crate::metamod::types::*;
 use crate::runtime::PluginRuntime;
 
 /// HLDS hostport cvar
 const CVAR_HOSTPORT: &[u8] = b"hostport\0";
 
+/// Trusted reverse proxy networks (comma separated CIDRs)
+const CVAR_TRUSTED_PROXIES: &[u8] = b"webxash_trusted_proxies\0";
+
+/// Load balancer networks that prepend a PROXY protocol header (comma separated CIDRs)
+const CVAR_PROXY_PROTOCOL: &[u8] = b"webxash_proxy_protocol\0";
+
+/// Browser origins allowed to use the HTTP API and signaling (comma separated)
+const CVAR_ALLOWED_ORIGINS: &[u8] = b"webxash_allowed_origins\0";
+
+/// URL prefix for all HTTP/WebSocket routes
+const CVAR_BASE_PATH: &[u8] = b"webxash_base_path\0";
+
+/// Game servers listed by the `/servers` directory (comma separated `host:port[=signaling_url]`)
+const CVAR_SERVER_LIST: &[u8] = b"webxash_server_list\0";
+
+/// Bearer token for the admin API (empty disables it)
+const CVAR_ADMIN_TOKEN: &[u8] = b"webxash_admin_token\0";
+
+/// Web RCON users (comma separated `name:token[:command|command...]`)
+const CVAR_RCON_USERS: &[u8] = b"webxash_rcon_users\0";
+
+/// Bearer token for posting web chat messages (empty disables posting)
+const CVAR_CHAT_TOKEN: &[u8] = b"webxash_chat_token\0";
+
+/// Name prefix of web chat messages shown in-game
+const CVAR_CHAT_PREFIX: &[u8] = b"webxash_chat_prefix\0";
+
+/// Default delivery of the game data channels (`unreliable` or `reliable`)
+const CVAR_CHANNEL_RELIABILITY: &[u8] = b"webxash_channel_reliability\0";
+
+/// UDP port of the WebTransport (HTTP/3) listener (empty or 0 disables it)
+const CVAR_WEBTRANSPORT_PORT: &[u8] = b"webxash_webtransport_port\0";
+
+/// PEM certificate chain file for TLS (self-signed when empty)
+const CVAR_TLS_CERT: &[u8] = b"webxash_tls_cert\0";
+
+/// PEM private key file of the TLS certificate
+const CVAR_TLS_KEY: &[u8] = b"webxash_tls_key\0";
+
+/// STUN/TURN server URLs for peer connections (comma separated, empty for host candidates only)
+const CVAR_ICE_SERVERS: &[u8] = b"webxash_ice_servers\0";
+
+/// Static username of the TURN servers
+const CVAR_TURN_USERNAME: &[u8] = b"webxash_turn_username\0";
+
+/// Static credential of the TURN servers
+const CVAR_TURN_CREDENTIAL: &[u8] = b"webxash_turn_credential\0";
+
+/// Shared secret for time-limited TURN REST credentials
+const CVAR_TURN_SECRET: &[u8] = b"webxash_turn_secret\0";
+
+/// Lifetime of TURN REST credentials, in seconds
+const CVAR_TURN_TTL: &[u8] = b"webxash_turn_ttl\0";
+
+/// Longest accepted TURN REST credential lifetime (30 days)
+const MAX_TURN_TTL_SECS: u64 = 30 * 24 * 60 * 60;
+
+/// UDP and TCP port of the embedded TURN server (empty or 0 disables it)
+const CVAR_TURN_PORT: &[u8] = b"webxash_turn_port\0";
+
+/// Concurrent relay allocations per TURN client (0 for no limit)
+const CVAR_TURN_MAX_ALLOCATIONS: &[u8] = b"webxash_turn_max_allocations\0";
+
+/// Relayed bytes per second per TURN client (0 for no limit)
+const CVAR_TURN_MAX_RATE: &[u8] = b"webxash_turn_max_rate\0";
+
+/// Local UDP ports of peer connections: one shared port or a `min-max` range (empty for any)
+const CVAR_ICE_PORTS: &[u8] = b"webxash_ice_ports\0";
+
+/// Age at which the DTLS certificate is replaced, in seconds (0 keeps it)
+const CVAR_DTLS_ROTATION: &[u8] = b"webxash_dtls_rotation\0";
+
+/// Game user message used to print web chat
+const USER_MSG_SAY_TEXT: &[u8] = b"SayText\0";
+
+/// Interval between server info refreshes, in game seconds
+const INFO_REFRESH_INTERVAL: f32 = 1.0;
+
+/// Plugin cvars registered on game init, with their default values
+const PLUGIN_CVARS: &[(&[u8], &[u8], c_int)] = &[
+    (CVAR_TRUSTED_PROXIES, b"\0", FCVAR_EXTDLL),
+    (CVAR_PROXY_PROTOCOL, b"\0", FCVAR_EXTDLL),
+    (CVAR_ALLOWED_ORIGINS, b"\0", FCVAR_EXTDLL),
+    (CVAR_BASE_PATH, b"\0", FCVAR_EXTDLL),
+    (CVAR_SERVER_LIST, b"\0", FCVAR_EXTDLL),
+    (CVAR_ADMIN_TOKEN, b"\0", FCVAR_EXTDLL | FCVAR_PROTECTED),
+    (CVAR_RCON_USERS, b"\0", FCVAR_EXTDLL | FCVAR_PROTECTED),
+    (CVAR_CHAT_TOKEN, b"\0", FCVAR_EXTDLL | FCVAR_PROTECTED),
+    (CVAR_CHAT_PREFIX, b"[WEB]\0", FCVAR_EXTDLL),
+    (CVAR_CHANNEL_RELIABILITY, b"unreliable\0", FCVAR_EXTDLL),
+    (CVAR_WEBTRANSPORT_PORT, b"\0", FCVAR_EXTDLL),
+    (CVAR_TLS_CERT, b"\0", FCVAR_EXTDLL),
+    (CVAR_TLS_KEY, b"\0", FCVAR_EXTDLL | FCVAR_PROTECTED),
+    (CVAR_ICE_SERVERS, b"\0", FCVAR_EXTDLL),
+    (CVAR_TURN_USERNAME, b"\0", FCVAR_EXTDLL),
+    (CVAR_TURN_CREDENTIAL, b"\0", FCVAR_EXTDLL | FCVAR_PROTECTED),
+    (CVAR_TURN_SECRET, b"\0", FCVAR_EXTDLL | FCVAR_PROTECTED),
+    (CVAR_TURN_TTL, b"86400\0", FCVAR_EXTDLL),
+    (CVAR_TURN_PORT, b"\0", FCVAR_EXTDLL),
+    (CVAR_TURN_MAX_ALLOCATIONS, b"4\0", FCVAR_EXTDLL),
+    (CVAR_TURN_MAX_RATE, b"262144\0", FCVAR_EXTDLL),
+    (CVAR_ICE_PORTS, b"\0", FCVAR_EXTDLL),
+    (CVAR_DTLS_ROTATION, b"2592000\0", FCVAR_EXTDLL),
+];
+
+/// Server console commands registered on game init
+const PLUGIN_COMMANDS: &[(&[u8], unsafe extern "C" fn())] =
+    &[(b"webxash_bridges\0", command_bridges)];
+
 /// Global plugin instance
 pub static PLUGIN: Lazy<Mutex<Plugin>> = Lazy::new(|| Mutex::new(Plugin::new()));
 
@@ -34,6 +153,10 @@ pub struct Plugin {
     config: PluginConfig,
     /// Whether the server is running
     running: bool,
+    /// Game time of the last server info refresh
+    last_info_refresh: f32,
+    /// `SayText` user message id, once the game DLL registered it
+    say_text_msg: Option<c_int>,
 }
 
 // SAFETY: Plugin is only accessed through a Mutex
@@ -52,6 +175,8 @@ impl Plugin {
             runtime: None,
             config: PluginConfig::default(),
             running: false,
+            last_info_refresh: 0.0,
+            say_text_msg: None,
         }
     }
 
@@ -74,6 +199,11 @@ impl Plugin {
         self.global_vars = global_vars;
     }
 
+    /// Engine function pointers (null before `GiveFnptrsToDll`).
+    pub fn engine_funcs(&self) -> *mut EngineFuncs {
+        self.engine_funcs
+    }
+
     /// Set meta utility functions.
     ///
     /// # Safety
@@ -142,6 +272,7 @@ impl Plugin {
     /// Called when the game initializes.
     pub fn on_game_init(&mut self) {
         self.register_cvars();
+        self.register_commands();
     }
 
     /// Called when the server activates (map load).
@@ -155,16 +286,306 @@ impl Plugin {
             // Reload config on map change
             self.config = self.load_config();
         }
+
+        game::reset_deaths();
+        self.refresh_server_info();
+        events::publish(EventKind::MapStart {
+            map: game::server_info().map,
+        });
+    }
+
+    /// Called at the start of every server frame.
+    pub fn on_start_frame(&mut self) {
+        game::record_frame();
+        self.deliver_chat();
+
+        if self.global_vars.is_null() {
+            return;
+        }
+
+        // SAFETY: global_vars checked above
+        let time = unsafe { (*self.global_vars).time };
+
+        // Game time restarts on map change
+        if time - self.last_info_refresh >= INFO_REFRESH_INTERVAL || time < self.last_info_refresh {
+            self.refresh_server_info();
+        }
+    }
+
+    /// Called when a client connects.
+    pub fn on_client_connect(&mut self, edict: *mut edict_t, name: String, address: String) {
+        if let Some(index) = self.entity_index(edict) {
+            events::publish(EventKind::Connect {
+                slot: index,
+                name,
+                web: bridge::is_bridge_addr(&address),
+            });
+            game::on_client_connect(index, address);
+        }
+    }
+
+    /// Called when a client disconnects.
+    pub fn on_client_disconnect(&mut self, edict: *mut edict_t) {
+        if let Some(index) = self.entity_index(edict) {
+            events::publish(EventKind::Disconnect {
+                slot: index,
+                name: self.player_name(edict),
+            });
+            game::on_client_disconnect(index);
+        }
+    }
+
+    /// Called when a client sends a command.
+    pub fn on_client_command(&mut self, edict: *mut edict_t) {
+        if !events::has_subscribers() || self.engine_funcs.is_null() {
+            return;
+        }
+        let Some(index) = self.entity_index(edict) else {
+            return;
+        };
+
+        // SAFETY: engine_funcs checked above, ClientCommand runs on the game thread
+        let (command, args) = unsafe {
+            let funcs = &*self.engine_funcs;
+            let (Some(argv), Some(all_args)) = (funcs.pfn_cmd_argv, funcs.pfn_cmd_args) else {
+                return;
+            };
+            (game::c_string(argv(0)), game::c_string(all_args()))
+        };
+
+        let team = match command.as_str() {
+            "say" => false,
+            "say_team" => true,
+            _ => return,
+        };
+
+        // Clients quote the message (`say "hello world"`)
+        let message = args.trim();
+        let message = message
+            .strip_prefix('"')
+            .and_then(|m| m.strip_suffix('"'))
+            .unwrap_or(message)
+            .trim();
+        if message.is_empty() {
+            return;
+        }
+
+        events::publish(EventKind::Chat {
+            slot: index,
+            name: self.player_name(edict),
+            team,
+            message: message.to_string(),
+            portal: false,
+        });
+    }
+
+    /// Print queued web chat messages in-game.
+    fn deliver_chat(&mut self) {
+        if self.engine_funcs.is_null() {
+            return;
+        }
+        let lines = chat::take_outgoing();
+        if lines.is_empty() {
+            return;
+        }
+
+        let say_text = self.say_text_msg();
+        // SAFETY: engine_funcs checked above, StartFrame runs on the game thread
+        unsafe { game::print_chat(&*self.engine_funcs, say_text, &lines) };
+    }
+
+    /// Look up the game's `SayText` user message id through Metamod.
+    fn say_text_msg(&mut self) -> Option<c_int> {
+        if self.say_text_msg.is_none() && !self.meta_util_funcs.is_null() {
+            // SAFETY: meta_util_funcs checked above, name is null-terminated
+            unsafe {
+                if let Some(get_user_msg_id) = (*self.meta_util_funcs).pfn_get_user_msg_id {
+                    let id = get_user_msg_id(
+                        ptr::addr_of!(PLUGIN_INFO),
+                        USER_MSG_SAY_TEXT.as_ptr().cast(),
+                        ptr::null_mut(),
+                    );
+                    // 0 until the game DLL registered its messages
+                    self.say_text_msg = (id > 0).then_some(id);
+                }
+            }
+        }
+        self.say_text_msg
+    }
+
+    /// Publish a fresh server info snapshot for the HTTP server.
+    fn refresh_server_info(&mut self) {
+        if self.engine_funcs.is_null() || self.global_vars.is_null() {
+            return;
+        }
+
+        // SAFETY: pointers checked above, called on the game thread
+        unsafe {
+            self.last_info_refresh = (*self.global_vars).time;
+            game::refresh_server_info(&*self.engine_funcs, &*self.global_vars);
+        }
+    }
+
+    /// Get the entity index of an edict.
+    fn entity_index(&self, edict: *mut edict_t) -> Option<std::ffi::c_int> {
+        if self.engine_funcs.is_null() || edict.is_null() {
+            return None;
+        }
+
+        // SAFETY: pointers checked above
+        unsafe {
+            let index_of_edict = (*self.engine_funcs).pfn_index_of_edict?;
+            Some(index_of_edict(edict))
+        }
+    }
+
+    /// Get the name of a player entity.
+    fn player_name(&self, edict: *mut edict_t) -> String {
+        if self.engine_funcs.is_null() || edict.is_null() {
+            return String::new();
+        }
+
+        // SAFETY: pointers checked above
+        unsafe {
+            let funcs = &*self.engine_funcs;
+            let (Some(get_vars_of_ent), Some(sz_from_index)) =
+                (funcs.pfn_get_vars_of_ent, funcs.pfn_sz_from_index)
+            else {
+                return String::new();
+            };
+
+            let vars = get_vars_of_ent(edict);
+            if vars.is_null() {
+                return String::new();
+            }
+            game::c_string(sz_from_index((*vars).netname))
+        }
+    }
+
+    /// Set the Metamod result for the current hook call.
+    pub fn set_meta_result(&self, result: MetaResult) {
+        if !self.meta_globals.is_null() {
+            // SAFETY: meta_globals checked above
+            unsafe {
+                (*self.meta_globals).mres = result;
+            }
+        }
     }
 
     /// Called when the server deactivates (map unload).
     pub fn on_server_deactivate(&mut self) {
-        // Nothing to do here for now
+        events::publish(EventKind::MapEnd {
+            map: game::server_info().map,
+        });
     }
 
     /// Register plugin cvars.
     fn register_cvars(&mut self) {
-        // CVars are optional - hostport is built-in to HLDS
+        if self.engine_funcs.is_null() {
+            return;
+        }
+
+        // SAFETY: engine_funcs checked above
+        unsafe {
+            let funcs = &*self.engine_funcs;
+            let (Some(cvar_register), Some(cvar_get_pointer)) =
+                (funcs.pfn_cvar_register, funcs.pfn_cvar_get_pointer)
+            else {
+                return;
+            };
+
+            for (name, default, flags) in PLUGIN_CVARS {
+                // Already registered (plugin reloaded without a restart)
+                if !cvar_get_pointer(name.as_ptr().cast()).is_null() {
+                    continue;
+                }
+
+                // The engine keeps the pointer for the rest of the process lifetime
+                let cvar = Box::leak(Box::new(cvar_t {
+                    name: name.as_ptr().cast(),
+                    string: default.as_ptr().cast_mut().cast(),
+                    flags: *flags,
+                    value: 0.0,
+                    next: ptr::null_mut(),
+                }));
+                cvar_register(cvar);
+            }
+        }
+    }
+
+    /// Register the plugin's server console commands.
+    fn register_commands(&self) {
+        if self.engine_funcs.is_null() {
+            return;
+        }
+
+        // SAFETY: engine_funcs checked above, names are null-terminated statics
+        unsafe {
+            let Some(add_server_command) = (*self.engine_funcs).pfn_add_server_command else {
+                return;
+            };
+
+            for (name, handler) in PLUGIN_COMMANDS {
+                add_server_command(name.as_ptr().cast(), *handler);
+            }
+        }
+    }
+
+    /// Print per-bridge traffic statistics to the server console.
+    fn print_bridge_stats(&self) {
+        let stats = bridge::all_stats();
+        self.server_print(&format!("[WEBXASH] {} active bridge(s)\n", stats.len()));
+
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| u64::try_from(d.as_millis()).unwrap_or(u64::MAX));
+        let idle = |last: Option<u64>| {
+            last.map_or_else(
+                || "-".to_string(),
+                |last| format!("{}s", now.saturating_sub(last) / 1000),
+            )
+        };
+
+        for s in stats {
+            self.server_print(&format!(
+                "  {} ({}, {})\n    to game: {} pkts, {} bytes, {} failed, idle {}\n    to client: {} pkts, {} bytes, {} failed, {} dropped, idle {}\n    peak rate: {} pkts/s\n",
+                s.client_id,
+                s.local_addr,
+                s.transport,
+                s.packets_to_game,
+                s.bytes_to_game,
+                s.send_failures_to_game,
+                idle(s.last_to_game),
+                s.packets_to_client,
+                s.bytes_to_client,
+                s.send_failures_to_client,
+                s.dropped_packets,
+                idle(s.last_to_client),
+                s.peak_packet_rate,
+            ));
+        }
+    }
+
+    /// Read a string cvar, returning `None` if it is unset or empty.
+    fn cvar_string(&self, name: &[u8]) -> Option<String> {
+        if self.engine_funcs.is_null() {
+            return None;
+        }
+
+        // SAFETY: engine_funcs checked above, name is null-terminated
+        unsafe {
+            let cvar_get_string = (*self.engine_funcs).pfn_cvar_get_string?;
+            let value = cvar_get_string(name.as_ptr().cast());
+            if value.is_null() {
+                return None;
+            }
+
+            let value = std::ffi::CStr::from_ptr(value)
+                .to_string_lossy()
+                .trim()
+                .to_string();
+            (!value.is_empty()).then_some(value)
+        }
     }
 
     /// Load configuration from cvars.
@@ -195,9 +616,102 @@ impl Plugin {
             }
         }
 
+        if let Some(proxies) = self.cvar_string(CVAR_TRUSTED_PROXIES) {
+            config.trusted_proxies = IpCidr::parse_list(&proxies);
+        }
+        if let Some(sources) = self.cvar_string(CVAR_PROXY_PROTOCOL) {
+            config.proxy_protocol_sources = IpCidr::parse_list(&sources);
+        }
+        if let Some(origins) = self.cvar_string(CVAR_ALLOWED_ORIGINS) {
+            config.allowed_origins = parse_origin_list(&origins);
+        }
+        if let Some(base_path) = self.cvar_string(CVAR_BASE_PATH) {
+            config.base_path = normalize_base_path(&base_path);
+        }
+        if let Some(servers) = self.cvar_string(CVAR_SERVER_LIST) {
+            config.directory_servers = parse_server_list(&servers);
+        }
+        config.admin_token = self.cvar_string(CVAR_ADMIN_TOKEN);
+        if let Some(users) = self.cvar_string(CVAR_RCON_USERS) {
+            config.rcon_users = parse_rcon_users(&users);
+        }
+        config.chat_token = self.cvar_string(CVAR_CHAT_TOKEN);
+        config.chat_prefix = self.cvar_string(CVAR_CHAT_PREFIX).unwrap_or_default();
+        if let Some(reliability) = self.cvar_string(CVAR_CHANNEL_RELIABILITY) {
+            match ChannelReliability::parse(&reliability) {
+                Some(reliability) => config.channel_reliability = reliability,
+                None => self.log_error(&format!(
+                    "Unknown webxash_channel_reliability \"{reliability}\", using unreliable"
+                )),
+            }
+        }
+        config.webtransport_port = self
+            .cvar_string(CVAR_WEBTRANSPORT_PORT)
+            .and_then(|port| port.parse().ok())
+            .filter(|&port| port != 0);
+        config.tls_cert = self.cvar_string(CVAR_TLS_CERT);
+        config.tls_key = self.cvar_string(CVAR_TLS_KEY);
+        self.load_webrtc_config(&mut config);
+
         config
     }
 
+    /// Read the ICE, TURN and DTLS cvars into `config`.
+    fn load_webrtc_config(&self, config: &mut PluginConfig) {
+        if let Some(servers) = self.cvar_string(CVAR_ICE_SERVERS) {
+            config.ice_servers = parse_ice_server_list(&servers);
+        }
+        config.turn_username = self.cvar_string(CVAR_TURN_USERNAME);
+        config.turn_credential = self.cvar_string(CVAR_TURN_CREDENTIAL);
+        config.turn_secret = self.cvar_string(CVAR_TURN_SECRET);
+        if let Some(ttl) = self
+            .cvar_string(CVAR_TURN_TTL)
+            .and_then(|ttl| ttl.parse::<u64>().ok())
+            .filter(|&ttl| ttl > 0)
+        {
+            config.turn_credential_ttl = Duration::from_secs(ttl.min(MAX_TURN_TTL_SECS));
+        }
+        config.turn_port = self
+            .cvar_string(CVAR_TURN_PORT)
+            .and_then(|port| port.parse().ok())
+            .filter(|&port| port != 0);
+        if let Some(max) = self
+            .cvar_string(CVAR_TURN_MAX_ALLOCATIONS)
+            .and_then(|max| max.parse().ok())
+        {
+            config.turn_max_allocations = max;
+        }
+        if let Some(rate) = self
+            .cvar_string(CVAR_TURN_MAX_RATE)
+            .and_then(|rate| rate.parse().ok())
+        {
+            config.turn_max_rate = rate;
+        }
+        if let Some(ports) = self.cvar_string(CVAR_ICE_PORTS) {
+            match IcePorts::parse(&ports) {
+                Some(ports) => config.ice_ports = ports,
+                None => self.log_error(&format!(
+                    "Invalid webxash_ice_ports \"{ports}\", using any port"
+                )),
+            }
+        }
+        if let Some(rotation) = self
+            .cvar_string(CVAR_DTLS_ROTATION)
+            .and_then(|rotation| rotation.parse().ok())
+        {
+            config.dtls_rotation = Some(rotation)
+                .filter(|&rotation| rotation > 0)
+                .map(Duration::from_secs);
+        }
+        let has_turn_credentials = config.turn_secret.is_some()
+            || (config.turn_username.is_some() && config.turn_credential.is_some());
+        if !has_turn_credentials && config.ice_servers.iter().any(|url| is_turn_url(url)) {
+            self.log_error(
+                "TURN servers in webxash_ice_servers need webxash_turn_secret or a username and credential; ignoring them",
+            );
+        }
+    }
+
     /// Log an info message.
     pub fn log_info(&self, msg: &str) {
         self.server_print(&format!("[WEBXASH] {msg}\n"));
@@ -225,13 +739,6 @@ impl Plugin {
             }
         }
     }
-
-    /// Get the game server address (localhost).
-    pub fn game_server_addr(&self) -> String {
-        // The game server is running on the same machine
-        // Default HLDS port is 27015
-        format!("127.0.0.1:{}", self.config.game_port)
-    }
 }
 
 impl Default for Plugin {
@@ -239,3 +746,8 @@ impl Default for Plugin {
         Self::new()
     }
 }
+
+/// `webxash_bridges` console command.
+unsafe extern "C" fn command_bridges() {
+    PLUGIN.lock().print_bridge_stats();
+}
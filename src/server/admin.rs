@@ -0,0 +1,245 @@
+//! Admin API (`/admin/*`).
+//!
+//! Every request must carry `Authorization: Bearer <token>` matching the
+//! `webxash_admin_token` cvar; the API is disabled when the cvar is empty.
+
+use serde::{Deserialize, Serialize};
+use sha1::{Digest, Sha1};
+use tokio::io::AsyncWriteExt;
+
+use crate::bridge::{self, BridgeStatsSnapshot};
+use crate::config::{IcePorts, PluginConfig};
+use crate::game;
+use crate::server::http::{error_json, url_decode, SharedState};
+use crate::server::metered::MeteredStream;
+use crate::server::request::HttpRequest;
+use crate::session::{self, SessionInfo};
+use crate::webrtc::{all_peer_stats, peer_stats, PeerStats};
+
+/// Session entry of `GET /admin/sessions`
+#[derive(Serialize)]
+struct SessionEntry {
+    #[serde(flatten)]
+    session: SessionInfo,
+    /// Bridge traffic, once the data channels are open
+    bridge: Option<BridgeStatsSnapshot>,
+    /// WebRTC transport stats, once collected
+    peer: Option<PeerStats>,
+}
+
+/// `POST /admin/command` request body
+#[derive(Deserialize)]
+struct CommandRequest {
+    command: String,
+}
+
+/// Handle a request under `/admin/`.
+pub async fn handle_admin_request(
+    stream: &mut MeteredStream,
+    request: &HttpRequest,
+    cors: &str,
+    state: &SharedState,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let (status, body) = match authorize(request, &state.config) {
+        Ok(()) => route(request, state).await,
+        Err((status, message)) => (status, error_json(message)),
+    };
+
+    let www_authenticate = if status.starts_with("401") {
+        "WWW-Authenticate: Bearer\r\n"
+    } else {
+        ""
+    };
+    let response = format!(
+        "HTTP/1.1 {status}\r\n{cors}\r\n{www_authenticate}Content-Type: application/json\r\nCache-Control: no-store\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        body.len(),
+        body
+    );
+    stream.write_all(response.as_bytes()).await?;
+
+    Ok(())
+}
+
+/// Dispatch an authorized request; returns the HTTP status and JSON body.
+async fn route(request: &HttpRequest, state: &SharedState) -> (&'static str, String) {
+    let method = request.method.as_str();
+    let path = request.path.trim_end_matches('/');
+
+    if let Some(client_id) = path.strip_prefix("/admin/sessions/") {
+        let client_id = url_decode(client_id);
+        return match method {
+            "DELETE" => kick_session(&client_id),
+            _ => method_not_allowed(),
+        };
+    }
+    if let Some(client_id) = path.strip_prefix("/admin/peers/") {
+        let client_id = url_decode(client_id);
+        return match (method, peer_stats(&client_id)) {
+            ("GET", Some(peer)) => ("200 OK", to_json(&peer)),
+            ("GET", None) => ("404 Not Found", error_json("Unknown client")),
+            _ => method_not_allowed(),
+        };
+    }
+
+    match (method, path) {
+        ("GET", "/admin/sessions") => ("200 OK", to_json(&list_sessions())),
+        ("GET", "/admin/peers") => ("200 OK", to_json(&all_peer_stats())),
+        ("POST", "/admin/command") => run_command(&request.body),
+        ("POST", "/admin/rescan") => {
+            let files = state.rescan_resources().await;
+            ("200 OK", serde_json::json!({ "files": files }).to_string())
+        }
+        ("GET", "/admin/config") => ("200 OK", config_json(&state.config)),
+        (
+            _,
+            "/admin/sessions" | "/admin/peers" | "/admin/command" | "/admin/rescan"
+            | "/admin/config",
+        ) => method_not_allowed(),
+        _ => ("404 Not Found", error_json("Not Found")),
+    }
+}
+
+fn list_sessions() -> Vec<SessionEntry> {
+    session::list()
+        .into_iter()
+        .map(|session| SessionEntry {
+            bridge: bridge::stats_for(&session.client_id),
+            peer: peer_stats(&session.client_id),
+            session,
+        })
+        .collect()
+}
+
+/// Close a web client's WebSocket, peer connection and bridge.
+fn kick_session(client_id: &str) -> (&'static str, String) {
+    if session::kick(client_id) {
+        println!("[WEBXASH] Admin kicked {client_id}");
+        (
+            "200 OK",
+            serde_json::json!({ "kicked": client_id }).to_string(),
+        )
+    } else {
+        ("404 Not Found", error_json("Unknown client"))
+    }
+}
+
+/// Queue a server command for the game thread.
+fn run_command(body: &[u8]) -> (&'static str, String) {
+    let request: CommandRequest = match serde_json::from_slice(body) {
+        Ok(request) => request,
+        Err(e) => return ("400 Bad Request", error_json(&format!("Invalid body: {e}"))),
+    };
+
+    match game::queue_server_command(&request.command) {
+        Ok(()) => {
+            println!("[WEBXASH] Admin queued command: {}", request.command.trim());
+            (
+                "202 Accepted",
+                serde_json::json!({ "queued": request.command.trim() }).to_string(),
+            )
+        }
+        Err(e) => ("400 Bad Request", error_json(e)),
+    }
+}
+
+/// Effective configuration; secrets are only reported as `*_set` flags.
+fn config_json(config: &PluginConfig) -> String {
+    let to_strings = |items: &[_]| {
+        items
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<String>>()
+    };
+
+    serde_json::json!({
+        "http_port": config.http_port,
+        "game_port": config.game_port,
+        "public_ip": config.public_ip,
+        "trusted_proxies": to_strings(&config.trusted_proxies),
+        "proxy_protocol_sources": to_strings(&config.proxy_protocol_sources),
+        "allowed_origins": config.allowed_origins,
+        "base_path": config.base_path,
+        "server_list": config
+            .directory_servers
+            .iter()
+            .map(|server| serde_json::json!({
+                "address": server.address,
+                "signaling_url": server.signaling_url,
+            }))
+            .collect::<Vec<_>>(),
+        "admin_token_set": config.admin_token.is_some(),
+        "rcon_enabled": config.admin_token.is_some() || !config.rcon_users.is_empty(),
+        "rcon_users": config
+            .rcon_users
+            .iter()
+            .map(|user| serde_json::json!({
+                "name": user.name,
+                "allowed_commands": user.allowed_commands,
+            }))
+            .collect::<Vec<_>>(),
+        "chat_enabled": config.chat_token.is_some() || config.admin_token.is_some(),
+        "chat_token_set": config.chat_token.is_some(),
+        "chat_prefix": config.chat_prefix,
+        "channel_reliability": config.channel_reliability.name(),
+        "webtransport_port": config.webtransport_port,
+        "tls_cert_set": config.tls_cert.is_some(),
+        "tls_key_set": config.tls_key.is_some(),
+        "ice_servers": config.ice_servers,
+        "ice_ports": match config.ice_ports {
+            IcePorts::Any => serde_json::Value::Null,
+            IcePorts::Single(port) => port.into(),
+            IcePorts::Range(min, max) => format!("{min}-{max}").into(),
+        },
+        "turn_username": config.turn_username,
+        "turn_credential_set": config.turn_credential.is_some(),
+        "turn_secret_set": config.turn_secret.is_some(),
+        "turn_ttl": config.turn_credential_ttl.as_secs(),
+        "turn_port": config.turn_port,
+        "turn_max_allocations": config.turn_max_allocations,
+        "turn_max_rate": config.turn_max_rate,
+        "dtls_rotation": config.dtls_rotation.map(|rotation| rotation.as_secs()),
+    })
+    .to_string()
+}
+
+fn method_not_allowed() -> (&'static str, String) {
+    ("405 Method Not Allowed", error_json("Method not allowed"))
+}
+
+fn to_json<T: Serialize>(value: &T) -> String {
+    serde_json::to_string(value).unwrap_or_default()
+}
+
+/// Check the bearer token; returns the HTTP status and error message on failure.
+fn authorize(
+    request: &HttpRequest,
+    config: &PluginConfig,
+) -> Result<(), (&'static str, &'static str)> {
+    let Some(expected) = config.admin_token.as_deref() else {
+        return Err(("403 Forbidden", "Admin API is disabled"));
+    };
+
+    match bearer_token(request) {
+        Some(token) if constant_time_eq(token, expected) => Ok(()),
+        Some(_) => Err(("401 Unauthorized", "Invalid token")),
+        None => Err(("401 Unauthorized", "Missing bearer token")),
+    }
+}
+
+/// Get the token of an `Authorization: Bearer` header.
+pub(super) fn bearer_token(request: &HttpRequest) -> Option<&str> {
+    request
+        .header("authorization")
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .map(str::trim)
+}
+
+/// Compare two secrets without leaking where they differ (or their lengths).
+pub(super) fn constant_time_eq(a: &str, b: &str) -> bool {
+    let a = Sha1::digest(a.as_bytes());
+    let b = Sha1::digest(b.as_bytes());
+    a.iter()
+        .zip(b.iter())
+        .fold(0u8, |diff, (x, y)| diff | (x ^ y))
+        == 0
+}
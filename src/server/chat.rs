@@ -0,0 +1,172 @@
+//! Web chat bridge (`/chat`).
+//!
+//! `POST /chat` and the `/chat` WebSocket post messages into the game; the
+//! WebSocket also streams in-game and web chat. Posting requires the
+//! `webxash_chat_token` (or the admin token).
+
+use std::net::{IpAddr, SocketAddr};
+use std::sync::Arc;
+
+use futures::{SinkExt, StreamExt};
+use serde::Deserialize;
+use tokio::io::AsyncWriteExt;
+use tokio::net::TcpStream;
+use tokio::sync::broadcast::error::RecvError;
+use tokio_tungstenite::{
+    tungstenite::{protocol::Role, Message},
+    WebSocketStream,
+};
+
+use crate::chat::{self, PostError};
+use crate::config::PluginConfig;
+use crate::events::{self, EventKind};
+use crate::server::admin::{bearer_token, constant_time_eq};
+use crate::server::http::error_json;
+use crate::server::metered::MeteredStream;
+use crate::server::request::HttpRequest;
+
+/// A chat message posted from the web
+#[derive(Deserialize)]
+struct ChatPost {
+    name: String,
+    message: String,
+}
+
+/// Messages sent by WebSocket clients
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum ClientMessage {
+    Auth {
+        token: String,
+    },
+    Say {
+        #[serde(flatten)]
+        post: ChatPost,
+    },
+}
+
+/// Handle `POST /chat`.
+pub async fn handle_chat_post(
+    stream: &mut MeteredStream,
+    request: &HttpRequest,
+    cors: &str,
+    config: &PluginConfig,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let (status, body) = if !posting_enabled(config) {
+        ("403 Forbidden", error_json("Chat posting is disabled"))
+    } else if !bearer_token(request).is_some_and(|token| token_valid(config, token)) {
+        (
+            "401 Unauthorized",
+            error_json("Invalid or missing bearer token"),
+        )
+    } else {
+        match serde_json::from_slice::<ChatPost>(&request.body) {
+            Ok(post) => post_result(config, request.client_addr.ip(), &post),
+            Err(e) => ("400 Bad Request", error_json(&format!("Invalid body: {e}"))),
+        }
+    };
+
+    let response = format!(
+        "HTTP/1.1 {status}\r\n{cors}\r\nContent-Type: application/json\r\nCache-Control: no-store\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        body.len(),
+        body
+    );
+    stream.write_all(response.as_bytes()).await?;
+
+    Ok(())
+}
+
+/// Handle a `/chat` WebSocket (handshake already completed).
+pub async fn handle_chat_socket(
+    stream: TcpStream,
+    config: Arc<PluginConfig>,
+    client_addr: SocketAddr,
+) {
+    let ws_stream = WebSocketStream::from_raw_socket(stream, Role::Server, None).await;
+    let (mut ws_sender, mut ws_receiver) = ws_stream.split();
+    let mut events = events::subscribe();
+    let mut authenticated = false;
+
+    loop {
+        let reply = tokio::select! {
+            msg = ws_receiver.next() => {
+                let text = match msg {
+                    Some(Ok(Message::Text(text))) => text,
+                    Some(Ok(Message::Close(_)) | Err(_)) | None => break,
+                    Some(Ok(_)) => continue,
+                };
+
+                match serde_json::from_str(&text) {
+                    Ok(ClientMessage::Auth { token }) => {
+                        authenticated = posting_enabled(&config) && token_valid(&config, &token);
+                        if authenticated {
+                            serde_json::json!({ "type": "authenticated" })
+                        } else {
+                            error_message("Authentication failed")
+                        }
+                    }
+                    Ok(ClientMessage::Say { post }) if authenticated => {
+                        match chat::post(
+                            &config.chat_prefix,
+                            client_addr.ip(),
+                            &post.name,
+                            &post.message,
+                        ) {
+                            // The message comes back as a chat event
+                            Ok(()) => continue,
+                            Err(e) => error_message(&e.to_string()),
+                        }
+                    }
+                    Ok(ClientMessage::Say { .. }) => error_message("Not authenticated"),
+                    Err(e) => error_message(&format!("Invalid message: {e}")),
+                }
+            }
+            event = events.recv() => match event {
+                Ok(event) if matches!(event.kind, EventKind::Chat { .. }) => {
+                    serde_json::to_value(&event).unwrap_or_default()
+                }
+                Ok(_) => continue,
+                Err(RecvError::Lagged(missed)) => {
+                    serde_json::json!({ "type": "lagged", "missed": missed })
+                }
+                Err(RecvError::Closed) => break,
+            },
+        };
+
+        if ws_sender
+            .send(Message::Text(reply.to_string()))
+            .await
+            .is_err()
+        {
+            break;
+        }
+    }
+}
+
+/// Queue a post; returns the HTTP status and JSON body.
+fn post_result(config: &PluginConfig, client: IpAddr, post: &ChatPost) -> (&'static str, String) {
+    match chat::post(&config.chat_prefix, client, &post.name, &post.message) {
+        Ok(()) => (
+            "202 Accepted",
+            serde_json::json!({ "queued": true }).to_string(),
+        ),
+        Err(e @ PostError::RateLimited) => ("429 Too Many Requests", error_json(&e.to_string())),
+        Err(e) => ("400 Bad Request", error_json(&e.to_string())),
+    }
+}
+
+fn posting_enabled(config: &PluginConfig) -> bool {
+    config.chat_token.is_some() || config.admin_token.is_some()
+}
+
+/// Check a token against the chat and admin tokens.
+fn token_valid(config: &PluginConfig, token: &str) -> bool {
+    [config.chat_token.as_deref(), config.admin_token.as_deref()]
+        .into_iter()
+        .flatten()
+        .any(|expected| constant_time_eq(token, expected))
+}
+
+fn error_message(message: &str) -> serde_json::Value {
+    serde_json::json!({ "type": "error", "message": message })
+}
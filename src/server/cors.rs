@@ -0,0 +1,63 @@
+//! CORS headers and Origin validation.
+
+/// CORS headers when no origin allowlist is configured
+const CORS_ANY_ORIGIN: &str = "\
+Access-Control-Allow-Origin: *\r\n\
+Access-Control-Allow-Methods: *\r\n\
+Access-Control-Allow-Headers: Authorization, *\r\n\
+Access-Control-Max-Age: 86400";
+
+/// CORS headers sent alongside an echoed allowed origin
+const CORS_ALLOWED_ORIGIN: &str = "\
+Vary: Origin\r\n\
+Access-Control-Allow-Methods: *\r\n\
+Access-Control-Allow-Headers: Authorization, *\r\n\
+Access-Control-Max-Age: 86400";
+
+/// Build the CORS headers for a request, or `None` if its origin is not allowed.
+///
+/// With an empty allowlist every origin is accepted (`*`). Otherwise a request
+/// carrying an `Origin` header must match an allowlist entry; requests without
+/// one (same-origin navigation, non-browser clients) are served without CORS grants.
+/// The returned string has no trailing CRLF.
+pub fn cors_headers(allowed_origins: &[String], origin: Option<&str>) -> Option<String> {
+    if allowed_origins.is_empty() {
+        return Some(CORS_ANY_ORIGIN.to_string());
+    }
+
+    match origin {
+        None => Some("Vary: Origin".to_string()),
+        Some(origin) if is_origin_allowed(allowed_origins, origin) => Some(format!(
+            "Access-Control-Allow-Origin: {origin}\r\n{CORS_ALLOWED_ORIGIN}"
+        )),
+        Some(_) => None,
+    }
+}
+
+/// Check an origin against the allowlist.
+///
+/// Entries are exact origins (`https://play.example.com`), `*` for any origin, or
+/// a wildcard subdomain pattern (`https://*.example.com`).
+fn is_origin_allowed(allowed_origins: &[String], origin: &str) -> bool {
+    let origin = origin.trim_end_matches('/');
+
+    allowed_origins.iter().any(|entry| {
+        if entry == "*" || entry.eq_ignore_ascii_case(origin) {
+            return true;
+        }
+
+        // "https://*.example.com" matches "https://a.example.com" but not "https://example.com"
+        match entry.split_once("*.") {
+            Some((scheme, domain)) => {
+                origin
+                    .get(..scheme.len())
+                    .is_some_and(|s| s.eq_ignore_ascii_case(scheme))
+                    && origin.len() > scheme.len() + domain.len() + 1
+                    && origin
+                        .to_lowercase()
+                        .ends_with(&format!(".{}", domain.to_lowercase()))
+            }
+            None => false,
+        }
+    })
+}
@@ -0,0 +1,128 @@
+//! Game event feed (`/events`) over Server-Sent Events or WebSocket.
+//!
+//! Subscribers choose event types with `?types=chat,kill`; WebSocket
+//! subscribers can also change the filter by sending `{"types": [...]}`.
+
+use std::time::Duration;
+
+use futures::{SinkExt, StreamExt};
+use serde::Deserialize;
+use tokio::io::AsyncWriteExt;
+use tokio::net::TcpStream;
+use tokio::sync::broadcast::error::RecvError;
+use tokio_tungstenite::{
+    tungstenite::{protocol::Role, Message},
+    WebSocketStream,
+};
+
+use crate::events::{self, EventFilter, GameEvent};
+use crate::server::metered::MeteredStream;
+use crate::server::request::HttpRequest;
+
+/// Interval of SSE keepalive comments (also detects closed connections)
+const KEEPALIVE_INTERVAL: Duration = Duration::from_secs(15);
+
+/// What to do with a received event
+enum Next {
+    Send(String),
+    Skip,
+    End,
+}
+
+/// Filter update sent by WebSocket subscribers
+#[derive(Deserialize)]
+struct FilterMessage {
+    types: Vec<String>,
+}
+
+/// Get the event filter of a request (`types` query parameter).
+pub fn event_filter(request: &HttpRequest) -> EventFilter {
+    EventFilter::parse(&request.query_param("types").unwrap_or_default())
+}
+
+/// Stream events as Server-Sent Events until the client goes away.
+pub async fn handle_event_stream(
+    stream: &mut MeteredStream,
+    cors: &str,
+    filter: EventFilter,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let mut events = events::subscribe();
+
+    let response = format!(
+        "HTTP/1.1 200 OK\r\n{cors}\r\nContent-Type: text/event-stream\r\nCache-Control: no-store\r\nConnection: close\r\n\r\n"
+    );
+    stream.write_all(response.as_bytes()).await?;
+
+    let mut keepalive = tokio::time::interval(KEEPALIVE_INTERVAL);
+    keepalive.tick().await;
+
+    loop {
+        let chunk = tokio::select! {
+            event = events.recv() => match next_message(event, &filter) {
+                Next::Send(json) => format!("data: {json}\n\n"),
+                Next::Skip => continue,
+                Next::End => break,
+            },
+            _ = keepalive.tick() => ": keepalive\n\n".to_string(),
+        };
+
+        // The client went away
+        if stream.write_all(chunk.as_bytes()).await.is_err() {
+            break;
+        }
+    }
+
+    Ok(())
+}
+
+/// Stream events over a WebSocket (handshake already completed).
+pub async fn handle_event_socket(stream: TcpStream, mut filter: EventFilter) {
+    let ws_stream = WebSocketStream::from_raw_socket(stream, Role::Server, None).await;
+    let (mut ws_sender, mut ws_receiver) = ws_stream.split();
+    let mut events = events::subscribe();
+
+    loop {
+        tokio::select! {
+            msg = ws_receiver.next() => match msg {
+                Some(Ok(Message::Text(text))) => match serde_json::from_str::<FilterMessage>(&text) {
+                    Ok(update) => filter = EventFilter::parse(&update.types.join(",")),
+                    Err(e) => {
+                        let error = serde_json::json!({
+                            "type": "error",
+                            "message": format!("Invalid message: {e}"),
+                        });
+                        if ws_sender.send(Message::Text(error.to_string())).await.is_err() {
+                            break;
+                        }
+                    }
+                },
+                Some(Ok(Message::Close(_)) | Err(_)) | None => break,
+                Some(Ok(_)) => {}
+            },
+            event = events.recv() => match next_message(event, &filter) {
+                Next::Send(json) => {
+                    if ws_sender.send(Message::Text(json)).await.is_err() {
+                        break;
+                    }
+                }
+                Next::Skip => {}
+                Next::End => break,
+            },
+        }
+    }
+}
+
+/// Decide what to do with a received event.
+fn next_message(event: Result<GameEvent, RecvError>, filter: &EventFilter) -> Next {
+    match event {
+        Ok(event) if filter.matches(&event) => {
+            serde_json::to_string(&event).map_or(Next::Skip, Next::Send)
+        }
+        Ok(_) => Next::Skip,
+        // Tell slow subscribers what they missed
+        Err(RecvError::Lagged(missed)) => {
+            Next::Send(serde_json::json!({ "type": "lagged", "missed": missed }).to_string())
+        }
+        Err(RecvError::Closed) => Next::End,
+    }
+}
@@ -0,0 +1,317 @@
+//! Client address resolution behind trusted reverse proxies.
+//!
+//! Honors `Forwarded` (RFC 7239), `X-Forwarded-For` and `X-Real-IP` headers,
+//! but only when the direct peer is in the trusted proxy list.
+
+use std::net::{IpAddr, SocketAddr};
+
+use crate::config::{contains_any, IpCidr};
+
+/// Determine the real client address for a request.
+///
+/// Forwarding headers are ignored unless `peer_addr` is a trusted proxy. The
+/// forwarding chain is walked from the nearest hop, skipping trusted proxies,
+/// so a client cannot spoof its address by prepending entries.
+pub fn resolve_client_addr(
+    peer_addr: SocketAddr,
+    headers: &[(String, String)],
+    trusted_proxies: &[IpCidr],
+) -> SocketAddr {
+    if !contains_any(trusted_proxies, peer_addr.ip()) {
+        return peer_addr;
+    }
+
+    let mut chain = header_values(headers, "forwarded")
+        .flat_map(parse_forwarded_for)
+        .collect::<Vec<_>>();
+
+    if chain.is_empty() {
+        chain = header_values(headers, "x-forwarded-for")
+            .flat_map(|value| value.split(','))
+            .filter_map(parse_node)
+            .collect();
+    }
+
+    if chain.is_empty() {
+        chain = header_values(headers, "x-real-ip")
+            .filter_map(parse_node)
+            .collect();
+    }
+
+    chain
+        .iter()
+        .rev()
+        .find(|addr| !contains_any(trusted_proxies, addr.ip()))
+        .or_else(|| chain.first())
+        .copied()
+        .unwrap_or(peer_addr)
+}
+
+/// Check whether a trusted proxy reports that the client connected over HTTPS.
+///
+/// Uses the `proto` parameter of the last `Forwarded` element, falling back to
+/// the last `X-Forwarded-Proto` value. Proxies append to both, so earlier
+/// entries may come from the client.
+pub fn is_forwarded_https(
+    peer_addr: SocketAddr,
+    headers: &[(String, String)],
+    trusted_proxies: &[IpCidr],
+) -> bool {
+    if !contains_any(trusted_proxies, peer_addr.ip()) {
+        return false;
+    }
+
+    let forwarded_proto = header_values(headers, "forwarded")
+        .flat_map(|value| value.split(','))
+        .last()
+        .and_then(|element| {
+            element
+                .split(';')
+                .filter_map(|pair| pair.split_once('='))
+                .find(|(key, _)| key.trim().eq_ignore_ascii_case("proto"))
+        })
+        .map(|(_, proto)| proto.trim().trim_matches('"'));
+
+    forwarded_proto
+        .or_else(|| {
+            header_values(headers, "x-forwarded-proto")
+                .flat_map(|value| value.split(','))
+                .last()
+                .map(str::trim)
+        })
+        .is_some_and(|proto| proto.eq_ignore_ascii_case("https"))
+}
+
+/// Iterate over all values of a header (case-insensitive name).
+fn header_values<'a>(
+    headers: &'a [(String, String)],
+    name: &'a str,
+) -> impl Iterator<Item = &'a str> {
+    headers
+        .iter()
+        .filter(move |(key, _)| key.eq_ignore_ascii_case(name))
+        .map(|(_, value)| value.as_str())
+}
+
+/// Extract the `for=` nodes from a `Forwarded` header value.
+fn parse_forwarded_for(value: &str) -> Vec<SocketAddr> {
+    value
+        .split(',')
+        .flat_map(|element| element.split(';'))
+        .filter_map(|pair| {
+            let (key, node) = pair.split_once('=')?;
+            if key.trim().eq_ignore_ascii_case("for") {
+                parse_node(node)
+            } else {
+                None
+            }
+        })
+        .collect()
+}
+
+/// Parse a forwarding node such as `1.2.3.4`, `1.2.3.4:5678`, `"[::1]:80"` or `::1`.
+///
+/// Obfuscated identifiers (`unknown`, `_hidden`) are rejected. A missing port becomes 0.
+fn parse_node(node: &str) -> Option<SocketAddr> {
+    let node = node.trim().trim_matches('"');
+
+    if let Ok(addr) = node.parse::<SocketAddr>() {
+        return Some(addr);
+    }
+
+    let ip = node.trim_start_matches('[').trim_end_matches(']');
+    ip.parse::<IpAddr>().ok().map(|ip| SocketAddr::new(ip, 0))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PROXY: &str = "10.0.0.2:40000";
+
+    fn trusted() -> Vec<IpCidr> {
+        IpCidr::parse_list("10.0.0.0/8")
+    }
+
+    fn headers(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+        pairs
+            .iter()
+            .map(|(key, value)| ((*key).to_string(), (*value).to_string()))
+            .collect()
+    }
+
+    fn resolve(peer: &str, pairs: &[(&str, &str)]) -> String {
+        resolve_client_addr(peer.parse().unwrap(), &headers(pairs), &trusted()).to_string()
+    }
+
+    #[test]
+    fn untrusted_peer_cannot_set_its_address() {
+        let peer = "203.0.113.7:5000";
+        assert_eq!(resolve(peer, &[("X-Forwarded-For", "198.51.100.1")]), peer);
+        assert_eq!(resolve(peer, &[("Forwarded", "for=198.51.100.1")]), peer);
+        assert_eq!(resolve(peer, &[("X-Real-IP", "198.51.100.1")]), peer);
+    }
+
+    #[test]
+    fn x_forwarded_for_from_trusted_proxy() {
+        assert_eq!(
+            resolve(PROXY, &[("x-forwarded-for", "198.51.100.1")]),
+            "198.51.100.1:0"
+        );
+    }
+
+    #[test]
+    fn spoofed_entries_before_the_nearest_untrusted_hop_are_ignored() {
+        // The client sent "X-Forwarded-For: 1.1.1.1" and the proxy appended its address
+        assert_eq!(
+            resolve(PROXY, &[("X-Forwarded-For", "1.1.1.1, 198.51.100.1")]),
+            "198.51.100.1:0"
+        );
+        // Split across several header lines
+        assert_eq!(
+            resolve(
+                PROXY,
+                &[
+                    ("X-Forwarded-For", "1.1.1.1"),
+                    ("X-Forwarded-For", "198.51.100.1, 10.0.0.3")
+                ]
+            ),
+            "198.51.100.1:0"
+        );
+    }
+
+    #[test]
+    fn trusted_hops_are_skipped() {
+        assert_eq!(
+            resolve(
+                PROXY,
+                &[("X-Forwarded-For", "198.51.100.1, 10.0.0.5, 10.0.0.4")]
+            ),
+            "198.51.100.1:0"
+        );
+        // Only trusted hops: the furthest one is the client
+        assert_eq!(
+            resolve(PROXY, &[("X-Forwarded-For", "10.0.0.5, 10.0.0.4")]),
+            "10.0.0.5:0"
+        );
+    }
+
+    #[test]
+    fn invalid_entries_fall_back_to_the_peer() {
+        assert_eq!(
+            resolve(PROXY, &[("X-Forwarded-For", "unknown, garbage")]),
+            PROXY
+        );
+        assert_eq!(resolve(PROXY, &[("X-Forwarded-For", "")]), PROXY);
+        assert_eq!(resolve(PROXY, &[("Forwarded", "for=_hidden")]), PROXY);
+        assert_eq!(resolve(PROXY, &[]), PROXY);
+    }
+
+    #[test]
+    fn garbage_in_the_chain_does_not_hide_the_client() {
+        assert_eq!(
+            resolve(PROXY, &[("X-Forwarded-For", "198.51.100.1, not-an-ip")]),
+            "198.51.100.1:0"
+        );
+    }
+
+    #[test]
+    fn forwarded_header_takes_precedence() {
+        assert_eq!(
+            resolve(
+                PROXY,
+                &[
+                    ("X-Forwarded-For", "192.0.2.9"),
+                    (
+                        "Forwarded",
+                        "for=1.1.1.1;proto=http, for=\"[2001:db8::1]:4711\";by=10.0.0.2"
+                    )
+                ]
+            ),
+            "[2001:db8::1]:4711"
+        );
+        assert_eq!(
+            resolve(PROXY, &[("Forwarded", "For=\"198.51.100.1:51234\"")]),
+            "198.51.100.1:51234"
+        );
+        assert_eq!(
+            resolve(PROXY, &[("Forwarded", "for=\"[2001:db8::1]\"")]),
+            "[2001:db8::1]:0"
+        );
+    }
+
+    #[test]
+    fn forwarded_without_for_falls_back_to_x_forwarded_for() {
+        assert_eq!(
+            resolve(
+                PROXY,
+                &[
+                    ("Forwarded", "proto=https;by=10.0.0.2"),
+                    ("X-Forwarded-For", "198.51.100.1")
+                ]
+            ),
+            "198.51.100.1:0"
+        );
+    }
+
+    #[test]
+    fn x_real_ip_is_the_last_resort() {
+        assert_eq!(
+            resolve(PROXY, &[("X-Real-IP", "198.51.100.1")]),
+            "198.51.100.1:0"
+        );
+        assert_eq!(
+            resolve(
+                PROXY,
+                &[
+                    ("X-Real-IP", "192.0.2.9"),
+                    ("X-Forwarded-For", "198.51.100.1")
+                ]
+            ),
+            "198.51.100.1:0"
+        );
+    }
+
+    #[test]
+    fn https_is_only_believed_from_trusted_proxies() {
+        let https = |peer: &str, pairs: &[(&str, &str)]| {
+            is_forwarded_https(peer.parse().unwrap(), &headers(pairs), &trusted())
+        };
+
+        assert!(https(PROXY, &[("X-Forwarded-Proto", "https")]));
+        assert!(https(
+            PROXY,
+            &[("Forwarded", "for=198.51.100.1;proto=\"HTTPS\"")]
+        ));
+        // The client sent "https" through a plain HTTP proxy that appends
+        assert!(!https(PROXY, &[("X-Forwarded-Proto", "https, http")]));
+        assert!(https(PROXY, &[("X-Forwarded-Proto", "http, https")]));
+        assert!(!https(
+            PROXY,
+            &[
+                ("X-Forwarded-Proto", "https"),
+                ("X-Forwarded-Proto", "http")
+            ]
+        ));
+        assert!(!https(
+            PROXY,
+            &[(
+                "Forwarded",
+                "for=1.1.1.1;proto=https, for=198.51.100.1;proto=http"
+            )]
+        ));
+        assert!(!https(
+            PROXY,
+            &[("Forwarded", "for=1.1.1.1;proto=https, for=198.51.100.1")]
+        ));
+        assert!(!https(
+            PROXY,
+            &[("Forwarded", "proto=http"), ("X-Forwarded-Proto", "https")]
+        ));
+        assert!(!https(PROXY, &[]));
+        assert!(!https(
+            "203.0.113.7:5000",
+            &[("X-Forwarded-Proto", "https")]
+        ));
+    }
+}
@@ -0,0 +1,110 @@
+//! Liveness and readiness checks for orchestrators.
+//!
+//! Liveness fails when the game thread stops running frames (hung HLDS).
+//! Readiness additionally requires the game port to answer an `A2S_INFO` query.
+
+use std::net::SocketAddr;
+use std::time::Duration;
+
+use serde::Serialize;
+
+use crate::a2s::{A2sCache, QueryKind};
+use crate::game;
+
+/// Frame gap after which the server is considered hung (map loads can stall for a while)
+const LIVENESS_FRAME_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Frame gap after which the server is not ready to accept players
+const READINESS_FRAME_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Health check response body
+#[derive(Serialize)]
+struct HealthResponse {
+    status: &'static str,
+    checks: Checks,
+}
+
+#[derive(Serialize)]
+struct Checks {
+    game_frame: Check,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    a2s: Option<Check>,
+}
+
+/// Result of a single check
+#[derive(Serialize)]
+struct Check {
+    ok: bool,
+    detail: String,
+}
+
+/// Run the liveness check; returns the HTTP status and JSON body.
+pub fn liveness() -> (&'static str, String) {
+    // The HTTP server starts on server activation, before the first frame
+    let game_frame = frame_check(LIVENESS_FRAME_TIMEOUT, true);
+    respond(Checks {
+        game_frame,
+        a2s: None,
+    })
+}
+
+/// Run the readiness check; returns the HTTP status and JSON body.
+pub async fn readiness(
+    a2s_cache: &A2sCache,
+    game_addr: Option<SocketAddr>,
+) -> (&'static str, String) {
+    let game_frame = frame_check(READINESS_FRAME_TIMEOUT, false);
+
+    let a2s = match game_addr {
+        Some(addr) => match a2s_cache.get(addr, QueryKind::Info).await {
+            Ok(_) => Check {
+                ok: true,
+                detail: format!("{addr} answered A2S_INFO"),
+            },
+            Err(e) => Check {
+                ok: false,
+                detail: format!("{addr}: {e}"),
+            },
+        },
+        None => Check {
+            ok: false,
+            detail: "invalid game server address".to_string(),
+        },
+    };
+
+    respond(Checks {
+        game_frame,
+        a2s: Some(a2s),
+    })
+}
+
+/// Check the age of the last game frame against `timeout`.
+fn frame_check(timeout: Duration, ok_before_first_frame: bool) -> Check {
+    match game::last_frame_age() {
+        Some(age) => Check {
+            ok: age <= timeout,
+            detail: format!("last frame {}ms ago", age.as_millis()),
+        },
+        None => Check {
+            ok: ok_before_first_frame,
+            detail: "no frame yet".to_string(),
+        },
+    }
+}
+
+fn respond(checks: Checks) -> (&'static str, String) {
+    let ok = checks.game_frame.ok && checks.a2s.as_ref().is_none_or(|check| check.ok);
+    let (status, text) = if ok {
+        ("200 OK", "ok")
+    } else {
+        ("503 Service Unavailable", "fail")
+    };
+
+    let body = serde_json::to_string(&HealthResponse {
+        status: text,
+        checks,
+    })
+    .unwrap_or_default();
+
+    (status, body)
+}
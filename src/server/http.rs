@@ -5,49 +5,156 @@
 use std::net::SocketAddr;
 use std::path::Path;
 use std::sync::Arc;
+use std::time::Duration;
 
+use once_cell::sync::OnceCell;
+use parking_lot::RwLock;
+use serde::Serialize;
 use sha1::{Digest, Sha1};
 use tokio::fs::File;
-use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::io::{AsyncReadExt, AsyncWriteExt, BufReader};
 use tokio::net::{TcpListener, TcpStream};
 
-use crate::config::PluginConfig;
+use crate::a2s::{A2sCache, QueryKind, ServerDirectory};
+use crate::config::{contains_any, PluginConfig};
+use crate::game::{self, ServerInfo};
+use crate::metrics;
+use crate::server::admin::handle_admin_request;
+use crate::server::chat::{handle_chat_post, handle_chat_socket};
+use crate::server::cors::cors_headers;
+use crate::server::events::{event_filter, handle_event_socket, handle_event_stream};
+use crate::server::forwarded::{is_forwarded_https, resolve_client_addr};
+use crate::server::health;
+use crate::server::metered::MeteredStream;
+use crate::server::proxy_protocol::read_proxy_header;
+use crate::server::rcon::handle_rcon;
+use crate::server::request::{read_request_body, read_request_head, strip_base_path, HttpRequest};
+use crate::server::scoreboard::{handle_scoreboard_stream, scoreboard_json};
 use crate::server::websocket::handle_websocket;
+use crate::server::webtransport;
+use crate::turn;
+use crate::webrtc::{dtls_fingerprint, Fingerprint, PeerFactory};
 
 /// Allowed asset folders for static file serving
 const ALLOWED_FOLDERS: &[&str] = &["sound", "sprites", "gfx", "maps", "models", "overviews"];
 
+/// Metrics label for paths that match no route
+const OTHER_ROUTE: &str = "other";
+
+/// How long A2S gateway results are reused before querying the game server again
+const A2S_CACHE_TTL: Duration = Duration::from_secs(2);
+
 /// HTTP/WebSocket server
 pub struct Server {
-    config: Arc<PluginConfig>,
-    /// Cached resources.jsonl content (generated once on startup)
-    resources_jsonl: Arc<String>,
+    state: Arc<SharedState>,
+}
+
+/// State shared by all connections
+pub(super) struct SharedState {
+    pub(super) config: Arc<PluginConfig>,
+    /// Cached resources.jsonl content (generated on startup and on rescan)
+    resources_jsonl: RwLock<Arc<String>>,
+    /// Recent A2S query results for the local game server
+    a2s_cache: A2sCache,
+    /// Polled status of the servers listed by `/servers`
+    directory: ServerDirectory,
+    /// Peer connection setup shared by signaling sessions, set when the server starts
+    peers: OnceCell<PeerFactory>,
+}
+
+impl SharedState {
+    /// Rescan the asset folders and replace resources.jsonl.
+    ///
+    /// Returns the number of files found.
+    pub(super) async fn rescan_resources(&self) -> usize {
+        let resources_jsonl = tokio::task::spawn_blocking(generate_resources_jsonl)
+            .await
+            .unwrap_or_default();
+        publish_resources(&self.resources_jsonl, resources_jsonl)
+    }
+}
+
+/// Log and store a freshly generated resources.jsonl; returns the number of files.
+fn publish_resources(slot: &RwLock<Arc<String>>, resources_jsonl: String) -> usize {
+    let file_count = resources_jsonl.lines().count();
+    println!(
+        "[WEBXASH] Generated resources.jsonl ({} bytes, {} files)",
+        resources_jsonl.len(),
+        file_count
+    );
+    metrics::RESOURCE_FILES.set(i64::try_from(file_count).unwrap_or(i64::MAX));
+    metrics::RESOURCE_INDEX_BYTES.set(i64::try_from(resources_jsonl.len()).unwrap_or(i64::MAX));
+
+    *slot.write() = Arc::new(resources_jsonl);
+    file_count
 }
 
 impl Server {
     /// Create a new server instance.
     pub fn new(config: PluginConfig) -> Self {
         // Generate resources.jsonl once at startup
-        let resources_jsonl = generate_resources_jsonl();
-        println!(
-            "[WEBXASH] Generated resources.jsonl ({} bytes, {} files)",
-            resources_jsonl.len(),
-            resources_jsonl.lines().count()
-        );
+        let resources_jsonl = RwLock::new(Arc::new(String::new()));
+        publish_resources(&resources_jsonl, generate_resources_jsonl());
+
+        let directory = ServerDirectory::new(config.directory_servers.clone());
 
         Self {
-            config: Arc::new(config),
-            resources_jsonl: Arc::new(resources_jsonl),
+            state: Arc::new(SharedState {
+                config: Arc::new(config),
+                resources_jsonl,
+                a2s_cache: A2sCache::new(A2S_CACHE_TTL),
+                directory,
+                peers: OnceCell::new(),
+            }),
         }
     }
 
     /// Run the server.
     pub async fn run(self) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-        let addr = SocketAddr::from(([0, 0, 0, 0], self.config.http_port));
+        let addr = SocketAddr::from(([0, 0, 0, 0], self.state.config.http_port));
         let listener = TcpListener::bind(addr).await?;
 
         println!("[WEBXASH] HTTP server listening on {addr}");
 
+        // Without it only WebRTC sessions fail, everything else keeps working
+        match PeerFactory::new(&self.state.config).await {
+            Ok(peers) => {
+                let _ = self.state.peers.set(peers);
+                if self.state.config.dtls_rotation.is_some() {
+                    let state = self.state.clone();
+                    tokio::spawn(async move {
+                        if let Some(peers) = state.peers.get() {
+                            peers.rotate_certificate().await;
+                        }
+                    });
+                }
+            }
+            Err(e) => eprintln!("[WEBXASH] WebRTC setup failed, signaling is disabled: {e}"),
+        }
+
+        if !self.state.directory.is_empty() {
+            let state = self.state.clone();
+            tokio::spawn(async move { state.directory.run().await });
+        }
+
+        if self.state.config.webtransport_port.is_some() {
+            let config = self.state.config.clone();
+            tokio::spawn(async move {
+                if let Err(e) = webtransport::run(config).await {
+                    eprintln!("[WEBXASH] WebTransport listener error: {e}");
+                }
+            });
+        }
+
+        if self.state.config.turn_port.is_some() {
+            let config = self.state.config.clone();
+            tokio::spawn(async move {
+                if let Err(e) = turn::run(config).await {
+                    eprintln!("[WEBXASH] TURN server error: {e}");
+                }
+            });
+        }
+
         loop {
             let (stream, peer_addr) = match listener.accept().await {
                 Ok(conn) => conn,
@@ -57,18 +164,37 @@ impl Server {
                 }
             };
 
-            let config = self.config.clone();
-            let resources_jsonl = self.resources_jsonl.clone();
+            let state = self.state.clone();
 
             tokio::spawn(async move {
-                if let Err(e) = handle_connection(stream, config, resources_jsonl, peer_addr).await
-                {
+                let mut stream = stream;
+                let mut peer_addr = peer_addr;
+
+                // Connections from PROXY protocol sources must carry the header
+                if contains_any(&state.config.proxy_protocol_sources, peer_addr.ip()) {
+                    match read_proxy_header(&mut stream).await {
+                        Ok(Some(client_addr)) => peer_addr = client_addr,
+                        Ok(None) => {}
+                        Err(e) => {
+                            eprintln!("[WEBXASH] Rejected connection from {peer_addr}: {e}");
+                            return;
+                        }
+                    }
+                }
+
+                // TURN over TCP shares the port with HTTP
+                if turn::is_turn_stream(&stream).await {
+                    turn::accept_tcp(stream, peer_addr);
+                    return;
+                }
+
+                if let Err(e) = handle_connection(stream, state, peer_addr).await {
                     // Ignore normal connection close errors
                     let err_str = e.to_string();
                     if !err_str.contains("connection closed")
                         && !err_str.contains("Connection reset")
                     {
-                        eprintln!("[WEBXASH] Connection error: {e}");
+                        eprintln!("[WEBXASH] Connection error from {peer_addr}: {e}");
                     }
                 }
             });
@@ -79,49 +205,85 @@ impl Server {
 /// Handle a TCP connection - determine if it's WebSocket or HTTP.
 async fn handle_connection(
     stream: TcpStream,
-    config: Arc<PluginConfig>,
-    resources_jsonl: Arc<String>,
+    state: Arc<SharedState>,
     peer_addr: SocketAddr,
 ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-    // Peek at the first bytes to determine request type
+    let config = &state.config;
     let mut buf_reader = BufReader::new(stream);
-    let mut first_line = String::new();
-
-    // Read the first line to check the request
-    buf_reader.read_line(&mut first_line).await?;
-
-    // Check if this looks like a WebSocket upgrade request
-    let is_websocket = first_line.contains("/ws") || first_line.contains("/websocket");
+    let Some((first_line, headers)) = read_request_head(&mut buf_reader).await? else {
+        return reject_request(
+            buf_reader.into_inner(),
+            "431 Request Header Fields Too Large",
+            "Request Header Fields Too Large",
+        )
+        .await;
+    };
+    let body = match read_request_body(&mut buf_reader, &headers).await? {
+        Ok(body) => body,
+        Err(err) => {
+            return reject_request(buf_reader.into_inner(), err.status(), err.message()).await
+        }
+    };
 
-    // Read remaining headers
-    let mut headers = String::new();
-    let mut upgrade_header = false;
-    let mut ws_key = String::new();
+    // Get the underlying stream back
+    let mut stream = MeteredStream::new(buf_reader.into_inner());
 
-    loop {
-        let mut line = String::new();
-        let n = buf_reader.read_line(&mut line).await?;
-        if n == 0 || line == "\r\n" || line == "\n" {
-            break;
-        }
+    let parts: Vec<&str> = first_line.split_whitespace().collect();
+    let method = parts.first().unwrap_or(&"GET").to_string();
+    let target = parts.get(1).unwrap_or(&"/");
+    let (raw_path, query) = target.split_once('?').unwrap_or((target, ""));
+
+    let route = strip_base_path(raw_path, &config.base_path).map_or(OTHER_ROUTE, route_label);
+
+    // Reject requests from origins outside the allowlist (including WebSocket upgrades)
+    let origin = headers
+        .iter()
+        .find(|(key, _)| key.eq_ignore_ascii_case("origin"))
+        .map(|(_, value)| value.as_str());
+    let Some(cors) = cors_headers(&config.allowed_origins, origin) else {
+        let result = send_error(
+            &mut stream,
+            "Vary: Origin",
+            "403 Forbidden",
+            "Origin not allowed",
+        )
+        .await;
+        stream.finish(route);
+        return result;
+    };
 
-        let line_lower = line.to_lowercase();
-        if line_lower.starts_with("upgrade:") && line_lower.contains("websocket") {
-            upgrade_header = true;
-        }
-        if line_lower.starts_with("sec-websocket-key:") {
-            ws_key = line.split(':').nth(1).unwrap_or("").trim().to_string();
-        }
+    // Everything outside the configured base path is not ours
+    let Some(path) = strip_base_path(raw_path, &config.base_path) else {
+        let result = send_error(&mut stream, &cors, "404 Not Found", "Not Found").await;
+        stream.finish(route);
+        return result;
+    };
 
-        headers.push_str(&line);
-    }
+    let request = HttpRequest {
+        method,
+        path: path.to_string(),
+        query: query.to_string(),
+        client_addr: resolve_client_addr(peer_addr, &headers, &config.trusted_proxies),
+        secure: is_forwarded_https(peer_addr, &headers, &config.trusted_proxies),
+        headers,
+        body,
+    };
 
-    // Get the underlying stream back
-    let stream = buf_reader.into_inner();
+    // Check if this looks like a WebSocket upgrade request
+    let is_websocket = matches!(
+        request.path.as_str(),
+        "/ws" | "/websocket" | "/rcon" | "/events" | "/chat"
+    );
+    let upgrade_header = request
+        .header("upgrade")
+        .is_some_and(|v| v.to_lowercase().contains("websocket"));
+    let ws_key = request
+        .header("sec-websocket-key")
+        .unwrap_or("")
+        .to_string();
 
     if is_websocket && upgrade_header && !ws_key.is_empty() {
         // Complete WebSocket handshake manually
-        let mut stream = stream;
         let accept_key = compute_websocket_accept_key(&ws_key);
         let response = format!(
             "HTTP/1.1 101 Switching Protocols\r\n\
@@ -129,41 +291,65 @@ async fn handle_connection(
              Connection: Upgrade\r\n\
              Sec-WebSocket-Accept: {accept_key}\r\n\r\n"
         );
-        stream.write_all(response.as_bytes()).await?;
+        let result = stream.write_all(response.as_bytes()).await;
+        let stream = stream.finish(route);
+        result?;
 
         // Handle WebSocket connection (handshake already completed)
-        let client_id = format!("{}-{}", peer_addr, uuid_simple());
-        handle_websocket(stream, config, client_id).await;
+        match request.path.as_str() {
+            "/rcon" => handle_rcon(stream, config.clone(), request.client_addr).await,
+            "/events" => handle_event_socket(stream, event_filter(&request)).await,
+            "/chat" => handle_chat_socket(stream, config.clone(), request.client_addr).await,
+            _ => {
+                let client_id = format!("{}-{}", request.client_addr, uuid_simple());
+                handle_websocket(
+                    stream,
+                    config.clone(),
+                    state.peers.get(),
+                    client_id,
+                    request.client_addr,
+                )
+                .await;
+            }
+        }
     } else {
-        // Handle HTTP request
-        handle_http_request(stream, &first_line, &config, &resources_jsonl).await?;
+        // Handle HTTP request, counting it even if the client went away mid-response
+        let result = handle_http_request(&mut stream, &request, &cors, &state).await;
+        stream.finish(route);
+        result?;
     }
 
     Ok(())
 }
 
-/// CORS headers for cross-origin requests
-const CORS_HEADERS: &str = "\
-Access-Control-Allow-Origin: *\r\n\
-Access-Control-Allow-Methods: *\r\n\
-Access-Control-Allow-Headers: *\r\n\
-Access-Control-Max-Age: 86400";
+/// Answer a request that is refused before it is parsed, and count it.
+async fn reject_request(
+    stream: TcpStream,
+    status: &str,
+    message: &str,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let mut stream = MeteredStream::new(stream);
+    let result = send_error(&mut stream, "Vary: Origin", status, message).await;
+    stream.finish(OTHER_ROUTE);
+    result
+}
 
 /// Handle a plain HTTP request.
 async fn handle_http_request(
-    mut stream: TcpStream,
-    first_line: &str,
-    _config: &PluginConfig,
-    resources_jsonl: &str,
+    stream: &mut MeteredStream,
+    request: &HttpRequest,
+    cors: &str,
+    state: &SharedState,
 ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-    let parts: Vec<&str> = first_line.split_whitespace().collect();
-    let method = parts.first().unwrap_or(&"GET");
-    let path = parts.get(1).unwrap_or(&"/");
+    let config = &state.config;
+    let resources_jsonl = state.resources_jsonl.read().clone();
+    let method = &request.method.as_str();
+    let path = &request.path.as_str();
 
     // Handle CORS preflight
     if *method == "OPTIONS" {
         let response = format!(
-            "HTTP/1.1 204 No Content\r\n{CORS_HEADERS}\r\nContent-Length: 0\r\nConnection: close\r\n\r\n"
+            "HTTP/1.1 204 No Content\r\n{cors}\r\nContent-Length: 0\r\nConnection: close\r\n\r\n"
         );
         stream.write_all(response.as_bytes()).await?;
         return Ok(());
@@ -171,13 +357,13 @@ async fn handle_http_request(
 
     // Handle static file serving for /cstrike/*
     if *method == "GET" && path.starts_with("/cstrike/") {
-        return serve_static_file(&mut stream, path).await;
+        return serve_static_file(stream, cors, path).await;
     }
 
     // Handle /resources.jsonl endpoint
     if *method == "GET" && *path == "/resources.jsonl" {
         let response = format!(
-            "HTTP/1.1 200 OK\r\n{CORS_HEADERS}\r\nContent-Type: application/x-ndjson\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            "HTTP/1.1 200 OK\r\n{cors}\r\nContent-Type: application/x-ndjson\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
             resources_jsonl.len(),
             resources_jsonl
         );
@@ -185,13 +371,62 @@ async fn handle_http_request(
         return Ok(());
     }
 
+    // Handle admin API
+    if path.starts_with("/admin/") {
+        return handle_admin_request(stream, request, cors, state).await;
+    }
+
+    // Handle the event feed (Server-Sent Events)
+    if *method == "GET" && *path == "/events" {
+        return handle_event_stream(stream, cors, event_filter(request)).await;
+    }
+
+    // Handle the scoreboard push stream
+    if *method == "GET" && *path == "/scoreboard/stream" {
+        return handle_scoreboard_stream(stream, cors).await;
+    }
+
+    // Handle web chat posts
+    if *method == "POST" && *path == "/chat" {
+        return handle_chat_post(stream, request, cors, config).await;
+    }
+
+    // Handle A2S query gateway
+    if *method == "GET" && path.starts_with("/a2s/") {
+        let (status, body) = a2s_query_json(state, path).await;
+        let response = format!(
+            "HTTP/1.1 {status}\r\n{cors}\r\nContent-Type: application/json\r\nCache-Control: max-age={}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            A2S_CACHE_TTL.as_secs(),
+            body.len(),
+            body
+        );
+        stream.write_all(response.as_bytes()).await?;
+        return Ok(());
+    }
+
     let (status, content_type, body) = match (*method, *path) {
-        ("GET", "/health") => ("200 OK", "text/plain", "OK".to_string()),
+        ("GET", "/health") => {
+            let (status, body) = health::liveness();
+            (status, "application/json", body)
+        }
+        ("GET", "/ready") => {
+            let game_addr = config.game_server_addr().parse().ok();
+            let (status, body) = health::readiness(&state.a2s_cache, game_addr).await;
+            (status, "application/json", body)
+        }
+        ("GET", "/info") => (
+            "200 OK",
+            "application/json",
+            server_info_json(request, config),
+        ),
+        ("GET", "/servers") => ("200 OK", "application/json", state.directory.to_json()),
+        ("GET", "/scoreboard") => ("200 OK", "application/json", scoreboard_json()),
+        ("GET", "/metrics") => ("200 OK", "text/plain; version=0.0.4", metrics::render()),
         _ => ("404 Not Found", "text/plain", "Not Found".to_string()),
     };
 
     let response = format!(
-        "HTTP/1.1 {status}\r\n{CORS_HEADERS}\r\nContent-Type: {content_type}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        "HTTP/1.1 {status}\r\n{cors}\r\nContent-Type: {content_type}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
         body.len(),
         body
     );
@@ -201,9 +436,142 @@ async fn handle_http_request(
     Ok(())
 }
 
+/// `GET /info` response body
+#[derive(Serialize)]
+struct InfoResponse {
+    #[serde(flatten)]
+    server: ServerInfo,
+    /// Plugin version
+    version: &'static str,
+    /// WebSocket signaling URL for this server
+    signaling_url: String,
+    /// WebTransport endpoint, when enabled
+    #[serde(skip_serializing_if = "Option::is_none")]
+    webtransport: Option<WebTransportInfo>,
+    /// Fingerprint of the DTLS certificate offers are made with
+    #[serde(skip_serializing_if = "Option::is_none")]
+    dtls_fingerprint: Option<Fingerprint>,
+}
+
+/// WebTransport part of `GET /info`
+#[derive(Serialize)]
+struct WebTransportInfo {
+    /// Session URL for `new WebTransport(url)`
+    url: String,
+    /// Hex SHA-256 for `serverCertificateHashes`, while the certificate is self-signed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    certificate_hash: Option<String>,
+}
+
+/// Build the server info JSON from the latest game thread snapshot.
+fn server_info_json(request: &HttpRequest, config: &PluginConfig) -> String {
+    let response = InfoResponse {
+        server: game::server_info(),
+        version: env!("CARGO_PKG_VERSION"),
+        signaling_url: signaling_url(request, config),
+        webtransport: config.webtransport_port.map(|port| WebTransportInfo {
+            url: webtransport_url(request, config, port),
+            certificate_hash: webtransport::certificate_hash(),
+        }),
+        dtls_fingerprint: dtls_fingerprint(),
+    };
+
+    serde_json::to_string(&response).unwrap_or_default()
+}
+
+/// Build the signaling URL as seen by the client.
+fn signaling_url(request: &HttpRequest, config: &PluginConfig) -> String {
+    let scheme = if request.secure { "wss" } else { "ws" };
+    let host = request.header("host").map_or_else(
+        || {
+            let ip = config.public_ip.as_deref().unwrap_or("127.0.0.1");
+            format!("{ip}:{}", config.http_port)
+        },
+        str::to_string,
+    );
+
+    format!("{scheme}://{host}{}/ws", config.base_path)
+}
+
+/// Build the WebTransport session URL as seen by the client.
+fn webtransport_url(request: &HttpRequest, config: &PluginConfig, port: u16) -> String {
+    let host = request.header("host").map_or_else(
+        || {
+            config
+                .public_ip
+                .as_deref()
+                .unwrap_or("127.0.0.1")
+                .to_string()
+        },
+        // Drop the HTTP port, keeping IPv6 brackets
+        |host| match host.rsplit_once(':') {
+            Some((name, _)) if !host.ends_with(']') => name.to_string(),
+            _ => host.to_string(),
+        },
+    );
+
+    format!(
+        "https://{host}:{port}{}{}",
+        config.base_path,
+        webtransport::SESSION_PATH
+    )
+}
+
+/// Metrics label for a request path, from a fixed set of routes.
+fn route_label(path: &str) -> &'static str {
+    match path {
+        "/health" => "/health",
+        "/ready" => "/ready",
+        "/info" => "/info",
+        "/servers" => "/servers",
+        "/metrics" => "/metrics",
+        "/resources.jsonl" => "/resources.jsonl",
+        "/ws" | "/websocket" => "/ws",
+        "/rcon" => "/rcon",
+        "/events" => "/events",
+        "/chat" => "/chat",
+        "/scoreboard" => "/scoreboard",
+        "/scoreboard/stream" => "/scoreboard/stream",
+        _ if path.starts_with("/cstrike/") => "/cstrike/*",
+        _ if path.starts_with("/a2s/") => "/a2s/*",
+        _ if path.starts_with("/admin/") => "/admin/*",
+        _ => OTHER_ROUTE,
+    }
+}
+
+/// Run (or reuse) an A2S query against the local game server.
+///
+/// Returns the HTTP status and JSON body.
+async fn a2s_query_json(state: &SharedState, path: &str) -> (&'static str, String) {
+    let kind = match path {
+        "/a2s/info" => QueryKind::Info,
+        "/a2s/players" => QueryKind::Players,
+        "/a2s/rules" => QueryKind::Rules,
+        _ => return ("404 Not Found", error_json("Unknown query")),
+    };
+
+    let Ok(addr) = state.config.game_server_addr().parse::<SocketAddr>() else {
+        return (
+            "500 Internal Server Error",
+            error_json("Invalid game server address"),
+        );
+    };
+
+    match state.a2s_cache.get(addr, kind).await {
+        Ok(body) => ("200 OK", body),
+        Err(e) => ("502 Bad Gateway", error_json(&e)),
+    }
+}
+
+/// Build a JSON error body.
+pub(super) fn error_json(message: &str) -> String {
+    serde_json::json!({ "error": message }).to_string()
+}
+
 /// Serve static files from cstrike folder.
 async fn serve_static_file(
-    stream: &mut TcpStream,
+    stream: &mut MeteredStream,
+    cors: &str,
     url_path: &str,
 ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
     // Remove /cstrike/ prefix to get relative path
@@ -214,14 +582,14 @@ async fn serve_static_file(
 
     // Security: check for path traversal
     if relative_path.contains("..") || relative_path.starts_with('/') {
-        send_error(stream, "403 Forbidden", "Access denied").await?;
+        send_error(stream, cors, "403 Forbidden", "Access denied").await?;
         return Ok(());
     }
 
     // Check if the first folder is in the allowed list
     let first_folder = relative_path.split('/').next().unwrap_or("");
     if !ALLOWED_FOLDERS.contains(&first_folder) {
-        send_error(stream, "403 Forbidden", "Folder not allowed").await?;
+        send_error(stream, cors, "403 Forbidden", "Folder not allowed").await?;
         return Ok(());
     }
 
@@ -232,7 +600,7 @@ async fn serve_static_file(
     let file = match File::open(&file_path).await {
         Ok(f) => f,
         Err(_) => {
-            send_error(stream, "404 Not Found", "File not found").await?;
+            send_error(stream, cors, "404 Not Found", "File not found").await?;
             return Ok(());
         }
     };
@@ -241,7 +609,13 @@ async fn serve_static_file(
     let metadata = match file.metadata().await {
         Ok(m) => m,
         Err(_) => {
-            send_error(stream, "500 Internal Server Error", "Cannot read file").await?;
+            send_error(
+                stream,
+                cors,
+                "500 Internal Server Error",
+                "Cannot read file",
+            )
+            .await?;
             return Ok(());
         }
     };
@@ -251,7 +625,7 @@ async fn serve_static_file(
 
     // Send headers
     let headers = format!(
-        "HTTP/1.1 200 OK\r\n{CORS_HEADERS}\r\nContent-Type: {content_type}\r\nContent-Length: {content_length}\r\nConnection: close\r\n\r\n"
+        "HTTP/1.1 200 OK\r\n{cors}\r\nContent-Type: {content_type}\r\nContent-Length: {content_length}\r\nConnection: close\r\n\r\n"
     );
     stream.write_all(headers.as_bytes()).await?;
 
@@ -271,12 +645,13 @@ async fn serve_static_file(
 
 /// Send an error response.
 async fn send_error(
-    stream: &mut TcpStream,
+    stream: &mut MeteredStream,
+    cors: &str,
     status: &str,
     message: &str,
 ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
     let response = format!(
-        "HTTP/1.1 {status}\r\n{CORS_HEADERS}\r\nContent-Type: text/plain\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{message}",
+        "HTTP/1.1 {status}\r\n{cors}\r\nContent-Type: text/plain\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{message}",
         message.len()
     );
     stream.write_all(response.as_bytes()).await?;
@@ -309,7 +684,7 @@ fn get_content_type(path: &str) -> &'static str {
 }
 
 /// Simple URL decode (handles %XX sequences).
-fn url_decode(s: &str) -> String {
+pub(super) fn url_decode(s: &str) -> String {
     let mut result = String::with_capacity(s.len());
     let mut chars = s.chars().peekable();
 
@@ -335,7 +710,7 @@ fn url_decode(s: &str) -> String {
 }
 
 /// Generate a simple UUID-like string.
-fn uuid_simple() -> String {
+pub(super) fn uuid_simple() -> String {
     use std::time::{SystemTime, UNIX_EPOCH};
 
     let now = SystemTime::now()
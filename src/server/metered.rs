@@ -0,0 +1,69 @@
+//! Response accounting for HTTP metrics.
+
+use std::io;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use tokio::io::AsyncWrite;
+use tokio::net::TcpStream;
+
+use crate::metrics;
+
+/// TCP stream that counts response bytes and remembers the response status.
+pub struct MeteredStream {
+    inner: TcpStream,
+    bytes: u64,
+    status: Option<u16>,
+}
+
+impl MeteredStream {
+    pub fn new(inner: TcpStream) -> Self {
+        Self {
+            inner,
+            bytes: 0,
+            status: None,
+        }
+    }
+
+    /// Record the response under `route` and return the underlying stream.
+    pub fn finish(self, route: &'static str) -> TcpStream {
+        if let Some(status) = self.status {
+            metrics::record_http_request(route, status);
+        }
+        metrics::HTTP_BYTES_SENT.add(self.bytes);
+        self.inner
+    }
+}
+
+/// Parse the status code from the start of a response (`HTTP/1.1 200 OK`).
+fn parse_status(buf: &[u8]) -> Option<u16> {
+    let code = buf.strip_prefix(b"HTTP/1.1 ")?.get(..3)?;
+    std::str::from_utf8(code).ok()?.parse().ok()
+}
+
+impl AsyncWrite for MeteredStream {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+        if this.status.is_none() {
+            this.status = parse_status(buf);
+        }
+
+        let result = Pin::new(&mut this.inner).poll_write(cx, buf);
+        if let Poll::Ready(Ok(n)) = result {
+            this.bytes += n as u64;
+        }
+        result
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.get_mut().inner).poll_flush(cx)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.get_mut().inner).poll_shutdown(cx)
+    }
+}
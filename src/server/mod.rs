@@ -1,6 +1,19 @@
 //! HTTP/WebSocket server for WebRTC signaling.
 
+mod admin;
+mod chat;
+mod cors;
+mod events;
+mod forwarded;
+mod health;
 mod http;
+mod metered;
+mod proxy_protocol;
+mod rcon;
+mod request;
+mod scoreboard;
+mod tls;
 mod websocket;
+mod webtransport;
 
 pub use http::Server;
@@ -0,0 +1,278 @@
+//! PROXY protocol (v1 and v2) header parsing.
+//!
+//! Load balancers such as `HAProxy` prepend a PROXY header to each TCP connection
+//! carrying the original client address. See
+//! <https://www.haproxy.org/download/2.9/doc/proxy-protocol.txt>.
+
+use std::io::{Error, ErrorKind};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::time::Duration;
+
+use tokio::io::{AsyncRead, AsyncReadExt};
+use tokio::net::TcpStream;
+
+/// Maximum time to wait for the PROXY header after accepting a connection
+const HEADER_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Maximum length of a v1 header including the trailing CRLF
+const V1_MAX_LENGTH: usize = 107;
+
+/// v2 binary header signature
+const V2_SIGNATURE: [u8; 12] = *b"\r\n\r\n\0\r\nQUIT\n";
+
+/// Read and consume a PROXY protocol header from the start of the stream.
+///
+/// Returns the original client address, or `None` if the header does not carry
+/// one (v1 `UNKNOWN`, v2 `LOCAL` or a non-TCP address family). Fails if the stream
+/// does not start with a valid header.
+pub async fn read_proxy_header(stream: &mut TcpStream) -> std::io::Result<Option<SocketAddr>> {
+    tokio::time::timeout(HEADER_TIMEOUT, read_header(stream))
+        .await
+        .map_err(|_| Error::new(ErrorKind::TimedOut, "timed out waiting for PROXY header"))?
+}
+
+async fn read_header<R: AsyncRead + Unpin>(stream: &mut R) -> std::io::Result<Option<SocketAddr>> {
+    // The shortest valid header (v1 "PROXY UNKNOWN\r\n") is longer than the v2 signature
+    let mut prefix = [0u8; 12];
+    stream.read_exact(&mut prefix).await?;
+
+    if prefix == V2_SIGNATURE {
+        read_v2(stream).await
+    } else if prefix.starts_with(b"PROXY ") {
+        read_v1(stream, &prefix).await
+    } else {
+        Err(invalid("missing PROXY protocol header"))
+    }
+}
+
+/// Parse the remainder of a text (v1) header.
+async fn read_v1<R: AsyncRead + Unpin>(
+    stream: &mut R,
+    prefix: &[u8],
+) -> std::io::Result<Option<SocketAddr>> {
+    let mut line = prefix.to_vec();
+    while !line.ends_with(b"\r\n") {
+        if line.len() >= V1_MAX_LENGTH {
+            return Err(invalid("PROXY v1 header too long"));
+        }
+        line.push(stream.read_u8().await?);
+    }
+
+    let line = std::str::from_utf8(&line[..line.len() - 2])
+        .map_err(|_| invalid("PROXY v1 header is not ASCII"))?;
+    let fields: Vec<&str> = line.split(' ').collect();
+
+    match fields.as_slice() {
+        ["PROXY", "UNKNOWN", ..] => Ok(None),
+        ["PROXY", "TCP4" | "TCP6", src_ip, _dst_ip, src_port, _dst_port] => {
+            let ip: IpAddr = src_ip
+                .parse()
+                .map_err(|_| invalid("invalid PROXY v1 source address"))?;
+            let port: u16 = src_port
+                .parse()
+                .map_err(|_| invalid("invalid PROXY v1 source port"))?;
+            Ok(Some(SocketAddr::new(ip, port)))
+        }
+        _ => Err(invalid("malformed PROXY v1 header")),
+    }
+}
+
+/// Parse the remainder of a binary (v2) header.
+async fn read_v2<R: AsyncRead + Unpin>(stream: &mut R) -> std::io::Result<Option<SocketAddr>> {
+    let mut head = [0u8; 4];
+    stream.read_exact(&mut head).await?;
+
+    let [version_command, family, len_hi, len_lo] = head;
+    if version_command >> 4 != 2 {
+        return Err(invalid("unsupported PROXY protocol version"));
+    }
+
+    let mut payload = vec![0u8; usize::from(u16::from_be_bytes([len_hi, len_lo]))];
+    stream.read_exact(&mut payload).await?;
+
+    match version_command & 0x0F {
+        // LOCAL command: health checks from the proxy itself
+        0x0 => return Ok(None),
+        // PROXY command
+        0x1 => {}
+        _ => return Err(invalid("unsupported PROXY v2 command")),
+    }
+
+    let addr = match family {
+        // TCP over IPv4: src addr, dst addr, src port, dst port
+        0x11 if payload.len() >= 12 => {
+            let ip = Ipv4Addr::new(payload[0], payload[1], payload[2], payload[3]);
+            let port = u16::from_be_bytes([payload[8], payload[9]]);
+            Some(SocketAddr::new(IpAddr::V4(ip), port))
+        }
+        // TCP over IPv6
+        0x21 if payload.len() >= 36 => {
+            let mut octets = [0u8; 16];
+            octets.copy_from_slice(&payload[..16]);
+            let port = u16::from_be_bytes([payload[32], payload[33]]);
+            Some(SocketAddr::new(IpAddr::V6(Ipv6Addr::from(octets)), port))
+        }
+        0x11 | 0x21 => return Err(invalid("truncated PROXY v2 address block")),
+        _ => None,
+    };
+
+    Ok(addr)
+}
+
+fn invalid(msg: &str) -> Error {
+    Error::new(ErrorKind::InvalidData, msg)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Parse a header from `input`, returning the result and the unread bytes.
+    async fn parse(input: &[u8]) -> (std::io::Result<Option<SocketAddr>>, Vec<u8>) {
+        let mut reader = input;
+        let result = read_header(&mut reader).await;
+        (result, reader.to_vec())
+    }
+
+    fn v2(command: u8, family: u8, payload: &[u8]) -> Vec<u8> {
+        let mut header = V2_SIGNATURE.to_vec();
+        header.push(0x20 | command);
+        header.push(family);
+        header.extend_from_slice(&u16::try_from(payload.len()).unwrap().to_be_bytes());
+        header.extend_from_slice(payload);
+        header
+    }
+
+    #[tokio::test]
+    async fn v1_tcp4_leaves_request_unread() {
+        let (result, rest) =
+            parse(b"PROXY TCP4 203.0.113.7 10.0.0.1 51234 27015\r\nGET / HTTP/1.1").await;
+        assert_eq!(result.unwrap(), Some("203.0.113.7:51234".parse().unwrap()));
+        assert_eq!(rest, b"GET / HTTP/1.1");
+    }
+
+    #[tokio::test]
+    async fn v1_tcp6() {
+        let (result, _) = parse(b"PROXY TCP6 2001:db8::1 2001:db8::2 4000 27015\r\n").await;
+        assert_eq!(result.unwrap(), Some("[2001:db8::1]:4000".parse().unwrap()));
+    }
+
+    #[tokio::test]
+    async fn v1_unknown_has_no_address() {
+        let (result, _) = parse(b"PROXY UNKNOWN\r\n").await;
+        assert_eq!(result.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn v1_malformed() {
+        for input in [
+            &b"PROXY TCP4 203.0.113.7 10.0.0.1 51234\r\n"[..],
+            b"PROXY TCP4 203.0.113.7 10.0.0.1 51234 27015 extra\r\n",
+            b"PROXY TCP4 not-an-ip 10.0.0.1 51234 27015\r\n",
+            b"PROXY TCP4 203.0.113.7 10.0.0.1 99999 27015\r\n",
+            b"PROXY UDP4 203.0.113.7 10.0.0.1 51234 27015\r\n",
+            b"PROXY TCP4 203.0.113.7  10.0.0.1 51234 27015\r\n",
+            b"PROXY TCP4 \xff\xfe 10.0.0.1 51234 27015\r\n",
+        ] {
+            let (result, _) = parse(input).await;
+            assert_eq!(
+                result.unwrap_err().kind(),
+                ErrorKind::InvalidData,
+                "{}",
+                String::from_utf8_lossy(input)
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn v1_too_long() {
+        let mut input = b"PROXY TCP4 ".to_vec();
+        input.extend(std::iter::repeat_n(b'1', 200));
+        input.extend_from_slice(b"\r\n");
+        let (result, _) = parse(&input).await;
+        assert_eq!(result.unwrap_err().kind(), ErrorKind::InvalidData);
+    }
+
+    #[tokio::test]
+    async fn v1_truncated() {
+        let (result, _) = parse(b"PROXY TCP4 203.0.113.7").await;
+        assert_eq!(result.unwrap_err().kind(), ErrorKind::UnexpectedEof);
+    }
+
+    #[tokio::test]
+    async fn missing_header() {
+        let (result, _) = parse(b"GET / HTTP/1.1\r\nHost: example.com\r\n\r\n").await;
+        assert_eq!(result.unwrap_err().kind(), ErrorKind::InvalidData);
+
+        let (result, _) = parse(b"PROXY").await;
+        assert_eq!(result.unwrap_err().kind(), ErrorKind::UnexpectedEof);
+    }
+
+    #[tokio::test]
+    async fn v2_tcp4_leaves_request_unread() {
+        let mut input = v2(
+            0x1,
+            0x11,
+            &[203, 0, 113, 7, 10, 0, 0, 1, 0xC8, 0x22, 0x69, 0x87],
+        );
+        input.extend_from_slice(b"GET");
+        let (result, rest) = parse(&input).await;
+        assert_eq!(result.unwrap(), Some("203.0.113.7:51234".parse().unwrap()));
+        assert_eq!(rest, b"GET");
+    }
+
+    #[tokio::test]
+    async fn v2_tcp6_with_tlvs() {
+        let mut payload = "2001:db8::1".parse::<Ipv6Addr>().unwrap().octets().to_vec();
+        payload.extend_from_slice(&"2001:db8::2".parse::<Ipv6Addr>().unwrap().octets());
+        payload.extend_from_slice(&[0x0F, 0xA0, 0x69, 0x87]);
+        // A TLV after the address block is skipped
+        payload.extend_from_slice(&[0x04, 0x00, 0x01, 0x00]);
+        let (result, rest) = parse(&v2(0x1, 0x21, &payload)).await;
+        assert_eq!(result.unwrap(), Some("[2001:db8::1]:4000".parse().unwrap()));
+        assert!(rest.is_empty());
+    }
+
+    #[tokio::test]
+    async fn v2_local_and_other_families_have_no_address() {
+        let (result, rest) = parse(&v2(0x0, 0x11, &[0; 12])).await;
+        assert_eq!(result.unwrap(), None);
+        assert!(rest.is_empty());
+
+        // AF_UNIX stream
+        let (result, _) = parse(&v2(0x1, 0x31, &[0; 216])).await;
+        assert_eq!(result.unwrap(), None);
+
+        // UDP over IPv4
+        let (result, _) = parse(&v2(0x1, 0x12, &[0; 12])).await;
+        assert_eq!(result.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn v2_truncated_address_block() {
+        let (result, _) = parse(&v2(0x1, 0x11, &[203, 0, 113, 7])).await;
+        assert_eq!(result.unwrap_err().kind(), ErrorKind::InvalidData);
+
+        let (result, _) = parse(&v2(0x1, 0x21, &[0; 20])).await;
+        assert_eq!(result.unwrap_err().kind(), ErrorKind::InvalidData);
+    }
+
+    #[tokio::test]
+    async fn v2_payload_shorter_than_length() {
+        let mut input = v2(0x1, 0x11, &[0; 12]);
+        input.truncate(input.len() - 4);
+        let (result, _) = parse(&input).await;
+        assert_eq!(result.unwrap_err().kind(), ErrorKind::UnexpectedEof);
+    }
+
+    #[tokio::test]
+    async fn v2_bad_version_and_command() {
+        let mut input = v2(0x1, 0x11, &[0; 12]);
+        input[12] = 0x11;
+        let (result, _) = parse(&input).await;
+        assert_eq!(result.unwrap_err().kind(), ErrorKind::InvalidData);
+
+        let (result, _) = parse(&v2(0x2, 0x11, &[0; 12])).await;
+        assert_eq!(result.unwrap_err().kind(), ErrorKind::InvalidData);
+    }
+}
@@ -0,0 +1,240 @@
+//! Web RCON console over WebSocket (`/rcon`).
+//!
+//! The client authenticates with its first message, then sends console
+//! commands and receives the server console output. Commands run on the game
+//! thread through the server command queue; every attempt is written to the
+//! audit log.
+
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use futures::{SinkExt, StreamExt};
+use serde::{Deserialize, Serialize};
+use tokio::io::AsyncWriteExt;
+use tokio::net::TcpStream;
+use tokio::sync::broadcast::error::RecvError;
+use tokio_tungstenite::{
+    tungstenite::{protocol::Role, Message},
+    WebSocketStream,
+};
+
+use crate::config::{PluginConfig, RconUser};
+use crate::console;
+use crate::game;
+use crate::server::admin::constant_time_eq;
+
+/// Audit log of web RCON commands (JSON lines, relative to the HLDS directory)
+const AUDIT_LOG_PATH: &str = "cstrike/addons/webxash/rcon_audit.log";
+
+/// Time allowed for the authentication message
+const AUTH_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Name of the user authenticated by the admin token
+const ADMIN_USER: &str = "admin";
+
+/// Messages sent by the browser
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum ClientMessage {
+    Auth { token: String },
+    Command { command: String },
+}
+
+/// Audit log entry
+#[derive(Serialize)]
+struct AuditEntry<'a> {
+    /// Unix time (ms)
+    time: u64,
+    user: &'a str,
+    client_addr: SocketAddr,
+    command: &'a str,
+    /// `queued`, `denied` or `rejected: <reason>`
+    result: &'a str,
+}
+
+type WsSender = futures::stream::SplitSink<WebSocketStream<TcpStream>, Message>;
+
+/// Handle a web RCON WebSocket connection (handshake already completed).
+pub async fn handle_rcon(stream: TcpStream, config: Arc<PluginConfig>, client_addr: SocketAddr) {
+    let ws_stream = WebSocketStream::from_raw_socket(stream, Role::Server, None).await;
+
+    if let Err(e) = run_console(ws_stream, &config, client_addr).await {
+        eprintln!("[WEBXASH] RCON error for {client_addr}: {e}");
+    }
+}
+
+async fn run_console(
+    ws_stream: WebSocketStream<TcpStream>,
+    config: &PluginConfig,
+    client_addr: SocketAddr,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let (mut ws_sender, mut ws_receiver) = ws_stream.split();
+
+    if config.admin_token.is_none() && config.rcon_users.is_empty() {
+        send_json(&mut ws_sender, &error_message("RCON is disabled")).await?;
+        ws_sender.send(Message::Close(None)).await?;
+        return Ok(());
+    }
+
+    // Browsers cannot set headers on WebSockets, so the token is the first message
+    let token = match tokio::time::timeout(AUTH_TIMEOUT, ws_receiver.next()).await {
+        Ok(Some(Ok(Message::Text(text)))) => match serde_json::from_str(&text) {
+            Ok(ClientMessage::Auth { token }) => Some(token),
+            _ => None,
+        },
+        _ => None,
+    };
+    let Some(user) = token.and_then(|token| authenticate(config, &token)) else {
+        audit("-", client_addr, "", "rejected: authentication failed").await;
+        send_json(&mut ws_sender, &error_message("Authentication failed")).await?;
+        ws_sender.send(Message::Close(None)).await?;
+        return Ok(());
+    };
+
+    println!(
+        "[WEBXASH] RCON session opened by {} ({client_addr})",
+        user.name
+    );
+    let mut output = console::subscribe();
+    send_json(
+        &mut ws_sender,
+        &serde_json::json!({
+            "type": "ready",
+            "user": user.name,
+            "allowed_commands": user.allowed_commands,
+        }),
+    )
+    .await?;
+
+    loop {
+        tokio::select! {
+            msg = ws_receiver.next() => {
+                let text = match msg {
+                    Some(Ok(Message::Text(text))) => text,
+                    Some(Ok(Message::Close(_))) | None => break,
+                    Some(Ok(_)) => continue,
+                    Some(Err(e)) => return Err(e.into()),
+                };
+
+                let reply = match serde_json::from_str(&text) {
+                    Ok(ClientMessage::Command { command }) => {
+                        run_command(&user, client_addr, &command).await
+                    }
+                    Ok(ClientMessage::Auth { .. }) => error_message("Already authenticated"),
+                    Err(e) => error_message(&format!("Invalid message: {e}")),
+                };
+                send_json(&mut ws_sender, &reply).await?;
+            }
+            line = output.recv() => {
+                let reply = match line {
+                    Ok(line) => serde_json::json!({
+                        "type": "output",
+                        "kind": line.kind,
+                        "text": line.text,
+                    }),
+                    Err(RecvError::Lagged(missed)) => {
+                        serde_json::json!({ "type": "lagged", "missed": missed })
+                    }
+                    Err(RecvError::Closed) => break,
+                };
+                send_json(&mut ws_sender, &reply).await?;
+            }
+        }
+    }
+
+    println!(
+        "[WEBXASH] RCON session closed by {} ({client_addr})",
+        user.name
+    );
+    Ok(())
+}
+
+/// Find the user a token belongs to.
+fn authenticate(config: &PluginConfig, token: &str) -> Option<RconUser> {
+    if config
+        .admin_token
+        .as_deref()
+        .is_some_and(|admin_token| constant_time_eq(token, admin_token))
+    {
+        return Some(RconUser {
+            name: ADMIN_USER.to_string(),
+            token: String::new(),
+            allowed_commands: Vec::new(),
+        });
+    }
+
+    config
+        .rcon_users
+        .iter()
+        .find(|user| constant_time_eq(token, &user.token))
+        .cloned()
+}
+
+/// Check, queue and audit a command; returns the reply to the client.
+async fn run_command(user: &RconUser, client_addr: SocketAddr, command: &str) -> serde_json::Value {
+    let command = command.trim();
+
+    if !user.allows(command) {
+        audit(&user.name, client_addr, command, "denied").await;
+        return error_message("Command not allowed");
+    }
+
+    match game::queue_server_command(command) {
+        Ok(()) => {
+            audit(&user.name, client_addr, command, "queued").await;
+            serde_json::json!({ "type": "queued", "command": command })
+        }
+        Err(e) => {
+            audit(&user.name, client_addr, command, &format!("rejected: {e}")).await;
+            error_message(e)
+        }
+    }
+}
+
+/// Print an audit entry and append it to the audit log.
+async fn audit(user: &str, client_addr: SocketAddr, command: &str, result: &str) {
+    println!("[WEBXASH] RCON {user} ({client_addr}): {command:?} {result}");
+
+    let entry = AuditEntry {
+        time: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| u64::try_from(d.as_millis()).unwrap_or(u64::MAX)),
+        user,
+        client_addr,
+        command,
+        result,
+    };
+    let Ok(mut line) = serde_json::to_string(&entry) else {
+        return;
+    };
+    line.push('\n');
+
+    if let Err(e) = append_audit_line(&line).await {
+        eprintln!("[WEBXASH] Failed to write {AUDIT_LOG_PATH}: {e}");
+    }
+}
+
+async fn append_audit_line(line: &str) -> std::io::Result<()> {
+    if let Some(dir) = std::path::Path::new(AUDIT_LOG_PATH).parent() {
+        tokio::fs::create_dir_all(dir).await?;
+    }
+
+    let mut file = tokio::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(AUDIT_LOG_PATH)
+        .await?;
+    file.write_all(line.as_bytes()).await
+}
+
+fn error_message(message: &str) -> serde_json::Value {
+    serde_json::json!({ "type": "error", "message": message })
+}
+
+async fn send_json(
+    ws_sender: &mut WsSender,
+    value: &serde_json::Value,
+) -> Result<(), tokio_tungstenite::tungstenite::Error> {
+    ws_sender.send(Message::Text(value.to_string())).await
+}
@@ -0,0 +1,157 @@
+//! HTTP request head parsing.
+
+use std::net::SocketAddr;
+
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, AsyncReadExt};
+
+use crate::server::http::url_decode;
+
+/// Maximum number of header lines accepted in a request
+const MAX_HEADERS: usize = 100;
+
+/// Maximum size of the request line and headers together
+const MAX_HEAD_SIZE: u64 = 16 * 1024;
+
+/// Maximum request body size (API requests only carry small JSON bodies)
+const MAX_BODY_SIZE: usize = 64 * 1024;
+
+/// Parsed HTTP request line and headers.
+#[derive(Debug)]
+pub struct HttpRequest {
+    /// Request method (e.g. `GET`)
+    pub method: String,
+    /// Request path with the configured base path stripped, without the query string
+    pub path: String,
+    /// Query string without the leading `?` (empty if none)
+    pub query: String,
+    /// Header name/value pairs in the order received
+    pub headers: Vec<(String, String)>,
+    /// Client address (after trusted proxy resolution)
+    pub client_addr: SocketAddr,
+    /// Whether the client reached us over TLS (reported by a trusted proxy)
+    pub secure: bool,
+    /// Request body (`Content-Length` bytes)
+    pub body: Vec<u8>,
+}
+
+impl HttpRequest {
+    /// Get the first header value with the given name (case-insensitive).
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Get the first query parameter with the given name, URL-decoded.
+    pub fn query_param(&self, name: &str) -> Option<String> {
+        self.query
+            .split('&')
+            .filter_map(|pair| pair.split_once('=').or(Some((pair, ""))))
+            .find(|(key, _)| *key == name)
+            .map(|(_, value)| url_decode(value))
+    }
+}
+
+/// Read the request line and headers from the stream.
+///
+/// Returns the raw request line and the header list, or `None` if the head is
+/// larger than `MAX_HEAD_SIZE` or has more than `MAX_HEADERS` header lines.
+pub async fn read_request_head<R: AsyncBufRead + Unpin>(
+    reader: &mut R,
+) -> std::io::Result<Option<(String, Vec<(String, String)>)>> {
+    let mut reader = reader.take(MAX_HEAD_SIZE);
+
+    let mut first_line = String::new();
+    reader.read_line(&mut first_line).await?;
+    if !first_line.ends_with('\n') && reader.limit() == 0 {
+        return Ok(None);
+    }
+
+    let mut headers = Vec::new();
+    loop {
+        let mut line = String::new();
+        let n = reader.read_line(&mut line).await?;
+        // A line cut off by the limit, not by the end of the stream
+        if !line.ends_with('\n') && reader.limit() == 0 {
+            return Ok(None);
+        }
+        if n == 0 || line == "\r\n" || line == "\n" {
+            break;
+        }
+
+        if headers.len() >= MAX_HEADERS {
+            return Ok(None);
+        }
+
+        if let Some((name, value)) = line.split_once(':') {
+            headers.push((name.trim().to_string(), value.trim().to_string()));
+        }
+    }
+
+    Ok(Some((first_line, headers)))
+}
+
+/// A request body refused before reading it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BodyRejection {
+    /// `Content-Length` is not a number
+    InvalidLength,
+    /// `Content-Length` is larger than `MAX_BODY_SIZE`
+    TooLarge,
+}
+
+impl BodyRejection {
+    /// HTTP status line of the rejection.
+    pub fn status(self) -> &'static str {
+        match self {
+            Self::InvalidLength => "400 Bad Request",
+            Self::TooLarge => "413 Payload Too Large",
+        }
+    }
+
+    /// Response body of the rejection.
+    pub fn message(self) -> &'static str {
+        match self {
+            Self::InvalidLength => "Invalid Content-Length",
+            Self::TooLarge => "Request body too large",
+        }
+    }
+}
+
+/// Read the request body announced by `Content-Length`.
+pub async fn read_request_body<R: AsyncBufRead + Unpin>(
+    reader: &mut R,
+    headers: &[(String, String)],
+) -> std::io::Result<Result<Vec<u8>, BodyRejection>> {
+    let Ok(length) = headers
+        .iter()
+        .find(|(key, _)| key.eq_ignore_ascii_case("content-length"))
+        .map_or(Ok(0), |(_, value)| value.parse::<usize>())
+    else {
+        return Ok(Err(BodyRejection::InvalidLength));
+    };
+
+    if length > MAX_BODY_SIZE {
+        return Ok(Err(BodyRejection::TooLarge));
+    }
+
+    let mut body = vec![0u8; length];
+    reader.read_exact(&mut body).await?;
+    Ok(Ok(body))
+}
+
+/// Strip the configured base path from a request path.
+///
+/// Returns `None` if the path is outside the base path.
+pub fn strip_base_path<'a>(path: &'a str, base_path: &str) -> Option<&'a str> {
+    if base_path.is_empty() {
+        return Some(path);
+    }
+
+    match path.strip_prefix(base_path) {
+        Some("") => Some("/"),
+        Some(rest) if rest.starts_with('/') => Some(rest),
+        _ => None,
+    }
+}
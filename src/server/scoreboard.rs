@@ -0,0 +1,53 @@
+//! Live scoreboard (`/scoreboard`, `/scoreboard/stream`).
+
+use std::time::Duration;
+
+use tokio::io::AsyncWriteExt;
+
+use crate::game;
+use crate::server::metered::MeteredStream;
+
+/// Interval of SSE keepalive comments (also detects closed connections)
+const KEEPALIVE_INTERVAL: Duration = Duration::from_secs(15);
+
+/// Current scoreboard as JSON.
+pub fn scoreboard_json() -> String {
+    serde_json::to_string(&*game::scoreboard()).unwrap_or_default()
+}
+
+/// Push the scoreboard as Server-Sent Events: now, then on every change.
+pub async fn handle_scoreboard_stream(
+    stream: &mut MeteredStream,
+    cors: &str,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let mut updates = game::subscribe_scoreboard();
+
+    let response = format!(
+        "HTTP/1.1 200 OK\r\n{cors}\r\nContent-Type: text/event-stream\r\nCache-Control: no-store\r\nConnection: close\r\n\r\n"
+    );
+    stream.write_all(response.as_bytes()).await?;
+
+    let mut keepalive = tokio::time::interval(KEEPALIVE_INTERVAL);
+    keepalive.tick().await;
+    updates.mark_changed();
+
+    loop {
+        let chunk = tokio::select! {
+            changed = updates.changed() => {
+                if changed.is_err() {
+                    break;
+                }
+                let scoreboard = updates.borrow_and_update().clone();
+                format!("data: {}\n\n", serde_json::to_string(&*scoreboard)?)
+            }
+            _ = keepalive.tick() => ": keepalive\n\n".to_string(),
+        };
+
+        // The client went away
+        if stream.write_all(chunk.as_bytes()).await.is_err() {
+            break;
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,80 @@
+//! TLS identity of the QUIC listener.
+//!
+//! Uses the configured PEM certificate and key. Without them a short-lived
+//! self-signed certificate is generated; browsers accept it through
+//! `serverCertificateHashes` as long as it is valid for at most 14 days.
+
+use std::fmt::Write;
+use std::time::{Duration, SystemTime};
+
+use rustls::pki_types::pem::PemObject;
+use rustls::pki_types::{CertificateDer, PrivateKeyDer};
+use sha2::{Digest, Sha256};
+
+use crate::config::PluginConfig;
+
+/// Validity of self-signed certificates (browsers allow at most 14 days)
+pub const SELF_SIGNED_VALIDITY: Duration = Duration::from_hours(13 * 24);
+
+/// Backdating of self-signed certificates, for clients with a slow clock
+const CLOCK_SKEW: Duration = Duration::from_hours(1);
+
+/// Certificate chain and private key
+pub struct TlsIdentity {
+    pub chain: Vec<CertificateDer<'static>>,
+    pub key: PrivateKeyDer<'static>,
+    /// Hex SHA-256 of the certificate if self-signed (for `serverCertificateHashes`)
+    pub self_signed_hash: Option<String>,
+}
+
+impl TlsIdentity {
+    /// Load the configured certificate, or generate a self-signed one.
+    pub fn load(config: &PluginConfig) -> Result<Self, Box<dyn std::error::Error + Send + Sync>> {
+        match (&config.tls_cert, &config.tls_key) {
+            (Some(cert), Some(key)) => {
+                let chain = CertificateDer::pem_file_iter(cert)?.collect::<Result<Vec<_>, _>>()?;
+                if chain.is_empty() {
+                    return Err(format!("No certificate in {cert}").into());
+                }
+
+                Ok(Self {
+                    chain,
+                    key: PrivateKeyDer::from_pem_file(key)?,
+                    self_signed_hash: None,
+                })
+            }
+            (Some(_), None) | (None, Some(_)) => {
+                Err("webxash_tls_cert and webxash_tls_key must be set together".into())
+            }
+            (None, None) => Self::self_signed(config),
+        }
+    }
+
+    /// Generate a self-signed ECDSA P-256 certificate.
+    fn self_signed(
+        config: &PluginConfig,
+    ) -> Result<Self, Box<dyn std::error::Error + Send + Sync>> {
+        let mut names = vec!["localhost".to_string()];
+        names.extend(config.public_ip.clone());
+
+        let mut params = rcgen::CertificateParams::new(names)?;
+        let now = SystemTime::now();
+        params.not_before = (now - CLOCK_SKEW).into();
+        params.not_after = (now + SELF_SIGNED_VALIDITY - CLOCK_SKEW).into();
+
+        let key_pair = rcgen::KeyPair::generate()?;
+        let cert = params.self_signed(&key_pair)?;
+
+        let hash = Sha256::digest(cert.der());
+        let self_signed_hash = hash.iter().fold(String::new(), |mut hex, byte| {
+            let _ = write!(hex, "{byte:02x}");
+            hex
+        });
+
+        Ok(Self {
+            chain: vec![cert.der().clone()],
+            key: PrivateKeyDer::Pkcs8(key_pair.serialize_der().into()),
+            self_signed_hash: Some(self_signed_hash),
+        })
+    }
+}
@@ -1,7 +1,11 @@
 //! WebSocket signaling handler.
 
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicU8, Ordering};
 use std::sync::Arc;
+use std::time::Instant;
 
+use futures::stream::SplitSink;
 use futures::{SinkExt, StreamExt};
 use serde::{Deserialize, Serialize};
 use tokio::net::TcpStream;
@@ -9,16 +13,37 @@ use tokio_tungstenite::{
     tungstenite::{protocol::Role, Message},
     WebSocketStream,
 };
-
-use crate::bridge::Bridge;
-use crate::config::PluginConfig;
-use crate::webrtc::create_peer_and_channels;
+use webrtc::data_channel::data_channel_state::RTCDataChannelState;
+use webrtc::data_channel::RTCDataChannel;
+use webrtc::ice_transport::ice_connection_state::RTCIceConnectionState;
+use webrtc::peer_connection::RTCPeerConnection;
+
+use crate::bridge::{Bridge, DataChannelTransport, Transport, WebSocketTransport};
+use crate::config::{ChannelReliability, PluginConfig};
+use crate::metrics;
+use crate::session::SessionGuard;
+use crate::turn;
+use crate::webrtc::{
+    create_channels, ice_servers, ChannelLayout, IceServer, PeerFactory, StatsCollector,
+    GAME_CHANNEL_ID,
+};
 
 /// Signal event types
 mod events {
     pub const OFFER: &str = "offer";
     pub const ANSWER: &str = "answer";
     pub const CANDIDATE: &str = "candidate";
+    pub const HELLO: &str = "hello";
+    pub const ERROR: &str = "error";
+}
+
+/// Error sent when the WebRTC API could not be set up
+const WEBRTC_UNAVAILABLE: &str = "WebRTC is not available, use the websocket transport";
+
+/// Transport names negotiated with `hello`
+mod transports {
+    pub const WEBRTC: &str = "webrtc";
+    pub const WEBSOCKET: &str = "websocket";
 }
 
 /// WebSocket signaling message
@@ -28,64 +53,520 @@ struct SignalMessage {
     data: serde_json::Value,
 }
 
+/// Client `hello` payload
+#[derive(Debug, Default, Deserialize)]
+struct ClientHello {
+    /// Requested transport, WebRTC if absent
+    #[serde(default)]
+    transport: Option<String>,
+    /// Requested data channel reliability, the server default if absent
+    #[serde(default)]
+    reliability: Option<String>,
+    /// Requested data channel layout, the write/read pair if absent
+    #[serde(default)]
+    channels: Option<String>,
+}
+
 /// Handle a WebSocket connection for signaling.
 ///
 /// Note: The TCP stream has already completed the WebSocket handshake in http.rs.
 /// We wrap it directly as a WebSocketStream since the HTTP 101 response was already sent.
-pub async fn handle_websocket(stream: TcpStream, config: Arc<PluginConfig>, client_id: String) {
+pub async fn handle_websocket(
+    stream: TcpStream,
+    config: Arc<PluginConfig>,
+    peers: Option<&PeerFactory>,
+    client_id: String,
+    client_addr: SocketAddr,
+) {
+    let started = Instant::now();
     println!("[WEBXASH] New WebSocket connection: {client_id}");
+    metrics::WS_SESSIONS.inc();
+    let session = SessionGuard::register(client_id.clone(), client_addr);
 
     // Wrap the stream as WebSocket (handshake already completed in http.rs)
     let ws_stream = WebSocketStream::from_raw_socket(stream, Role::Server, None).await;
 
     // Handle the signaling
-    if let Err(e) = handle_signaling(ws_stream, config, client_id.clone()).await {
+    if let Err(e) = handle_signaling(
+        ws_stream,
+        config,
+        peers,
+        client_id.clone(),
+        &session,
+        started,
+    )
+    .await
+    {
         eprintln!("[WEBXASH] Signaling error for {client_id}: {e}");
     }
 
+    metrics::WS_SESSIONS.dec();
     println!("[WEBXASH] WebSocket connection closed: {client_id}");
 }
 
+/// Sending half of the signaling WebSocket
+type WsSender = SplitSink<WebSocketStream<TcpStream>, Message>;
+
+type SignalingResult<T> = Result<T, Box<dyn std::error::Error + Send + Sync>>;
+
+/// Bridge of a signaling session
+#[derive(Default)]
+struct BridgeSlot {
+    /// Set once the session moved to the WebSocket transport
+    switched: bool,
+    bridge: Option<Arc<Bridge>>,
+}
+
+type SharedBridgeSlot = Arc<tokio::sync::Mutex<BridgeSlot>>;
+
 /// Handle WebRTC signaling over WebSocket.
 async fn handle_signaling(
     ws_stream: WebSocketStream<TcpStream>,
     config: Arc<PluginConfig>,
+    peers: Option<&PeerFactory>,
     client_id: String,
-) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-    let (mut ws_sender, mut ws_receiver) = ws_stream.split();
-
-    // Create peer connection and data channels
-    let (peer, write_channel, read_channel) =
-        create_peer_and_channels(config.public_ip.clone()).await?;
-
-    println!("[WEBXASH] Created peer connection for {client_id}");
-
-    // Create the offer
-    let offer = peer.create_offer(None).await?;
-    peer.set_local_description(offer.clone()).await?;
-
-    // Send offer to client
-    let offer_msg = SignalMessage {
-        event: events::OFFER.to_string(),
-        data: serde_json::json!({
-            "type": events::OFFER,
-            "sdp": offer.sdp
-        }),
+    session: &SessionGuard,
+    started: Instant,
+) -> SignalingResult<()> {
+    let (ws_sender, mut ws_receiver) = ws_stream.split();
+    let ws_sender = Arc::new(tokio::sync::Mutex::new(ws_sender));
+    let mut signaling = Signaling::start(ws_sender, config, peers, client_id, started).await?;
+    let client_id = signaling.client_id.clone();
+
+    // Handle incoming WebSocket messages until the client leaves or is kicked
+    let result = loop {
+        let msg = tokio::select! {
+            msg = ws_receiver.next() => msg,
+            () = session.kicked() => {
+                println!("[WEBXASH] Kicking {client_id}");
+                let _ = signaling.ws_sender.lock().await.send(Message::Close(None)).await;
+                break Ok(());
+            }
+        };
+        let Some(msg) = msg else {
+            break Ok(());
+        };
+
+        match msg {
+            Ok(Message::Text(text)) => {
+                if let Err(e) = signaling.on_text(&text).await {
+                    break Err(e);
+                }
+            }
+            Ok(Message::Close(_)) => {
+                println!("[WEBXASH] WebSocket close from {client_id}");
+                break Ok(());
+            }
+            Ok(Message::Binary(data)) => {
+                if let Some(transport) = &signaling.websocket {
+                    transport.deliver(data.into()).await;
+                }
+            }
+            Ok(Message::Ping(_)) => {
+                // Pong is handled automatically
+            }
+            Ok(_) => {}
+            Err(e) => {
+                eprintln!("[WEBXASH] WebSocket error from {client_id}: {e}");
+                break Ok(());
+            }
+        }
     };
 
-    let json = serde_json::to_string(&offer_msg)?;
-    ws_sender.send(Message::Text(json)).await?;
+    signaling.close().await;
+    result
+}
+
+/// State of one signaling session
+struct Signaling {
+    config: Arc<PluginConfig>,
+    client_id: String,
+    ws_sender: Arc<tokio::sync::Mutex<WsSender>>,
+    /// Peer connection, unset when WebRTC is not set up or after switching to WebSocket
+    webrtc: Option<WebRtcPeer>,
+    bridge: SharedBridgeSlot,
+    reliability: ChannelReliability,
+    layout: ChannelLayout,
+    /// Set once the client asked for binary WebSocket frames instead of WebRTC
+    websocket: Option<Arc<WebSocketTransport>>,
+}
+
+/// Peer connection of a signaling session and its data channels
+struct WebRtcPeer {
+    peer: Arc<RTCPeerConnection>,
+    ice_state: Arc<metrics::IceStateTracker>,
+    /// Collects transport stats for the admin API until the peer closes
+    _stats_collector: StatsCollector,
+    write_channel: Arc<RTCDataChannel>,
+    read_channel: Arc<RTCDataChannel>,
+    channels_open: Arc<AtomicU8>,
+}
+
+impl WebRtcPeer {
+    async fn close(self, client_id: &str) {
+        if let Err(e) = self.peer.close().await {
+            eprintln!("[WEBXASH] Failed to close peer connection for {client_id}: {e}");
+        }
+        self.ice_state.set(RTCIceConnectionState::Closed);
+    }
+}
+
+impl Signaling {
+    /// Start a session: send the offer, or without `peers` tell the client
+    /// that only the WebSocket transport is available.
+    async fn start(
+        ws_sender: Arc<tokio::sync::Mutex<WsSender>>,
+        config: Arc<PluginConfig>,
+        peers: Option<&PeerFactory>,
+        client_id: String,
+        started: Instant,
+    ) -> SignalingResult<Self> {
+        let mut signaling = Self {
+            reliability: config.channel_reliability,
+            layout: ChannelLayout::Pair,
+            config,
+            client_id,
+            ws_sender,
+            webrtc: None,
+            bridge: SharedBridgeSlot::default(),
+            websocket: None,
+        };
+
+        if let Some(peers) = peers {
+            signaling.start_webrtc(peers, started).await?;
+        } else {
+            println!(
+                "[WEBXASH] WebRTC is not set up, {} can only use the WebSocket transport",
+                signaling.client_id
+            );
+            signaling.send_error(WEBRTC_UNAVAILABLE).await?;
+        }
+        Ok(signaling)
+    }
+
+    /// Create the peer connection and its data channels, and send the offer.
+    async fn start_webrtc(&mut self, peers: &PeerFactory, started: Instant) -> SignalingResult<()> {
+        let client_id = &self.client_id;
+
+        // Create peer connection and data channels
+        let ice_servers = ice_servers(&self.config, client_id);
+        let (peer, write_channel, read_channel) = peers
+            .create_peer_and_channels(
+                ice_servers.iter().map(IceServer::to_rtc).collect(),
+                self.reliability,
+            )
+            .await?;
+
+        println!("[WEBXASH] Created peer connection for {client_id}");
+
+        // Count the peer under its ICE state until it closes
+        let ice_state = Arc::new(metrics::IceStateTracker::new());
+        ice_state.set(peer.ice_connection_state());
+        let ice_state_for_handler = ice_state.clone();
+        peer.on_ice_connection_state_change(Box::new(move |state| {
+            ice_state_for_handler.set(state);
+            Box::pin(async {})
+        }));
+
+        let stats_collector = StatsCollector::start(client_id.clone(), &peer);
+
+        // The browser may also relay through the embedded TURN server
+        let mut browser_ice_servers = ice_servers;
+        browser_ice_servers.extend(turn::ice_server(&self.config, client_id));
+
+        // Create the offer
+        let offer = peer.create_offer(None).await?;
+        peer.set_local_description(offer.clone()).await?;
+
+        // Send offer to client
+        let offer_msg = SignalMessage {
+            event: events::OFFER.to_string(),
+            data: serde_json::json!({
+                "type": events::OFFER,
+                "sdp": offer.sdp,
+                "reliability": self.reliability.name(),
+                "channels": self.layout.name(),
+                "iceServers": browser_ice_servers
+            }),
+        };
+
+        let json = serde_json::to_string(&offer_msg)?;
+        self.ws_sender
+            .lock()
+            .await
+            .send(Message::Text(json))
+            .await?;
+
+        let time_to_offer = started.elapsed();
+        metrics::observe_offer(time_to_offer);
+        println!(
+            "[WEBXASH] Sent offer to {client_id} in {} ms",
+            time_to_offer.as_millis()
+        );
+
+        send_ice_candidates(&peer, &self.ws_sender, client_id);
+
+        self.webrtc = Some(WebRtcPeer {
+            peer,
+            ice_state,
+            _stats_collector: stats_collector,
+            write_channel,
+            read_channel,
+            channels_open: Arc::new(AtomicU8::new(0)),
+        });
+        self.bridge_when_open();
+        Ok(())
+    }
+
+    /// Handle a text signaling message.
+    async fn on_text(&mut self, text: &str) -> SignalingResult<()> {
+        let signal: SignalMessage = match serde_json::from_str(text) {
+            Ok(s) => s,
+            Err(e) => {
+                eprintln!(
+                    "[WEBXASH] Invalid signal message from {}: {e}",
+                    self.client_id
+                );
+                return Ok(());
+            }
+        };
+
+        match signal.event.as_str() {
+            events::HELLO => {
+                let hello: ClientHello = serde_json::from_value(signal.data).unwrap_or_default();
+                self.on_hello(hello).await?;
+            }
+            events::ANSWER | events::CANDIDATE if self.webrtc.is_none() => {
+                // The peer connection is gone after switching to WebSocket
+                if self.websocket.is_none() {
+                    self.send_error(WEBRTC_UNAVAILABLE).await?;
+                }
+            }
+            events::ANSWER => {
+                let sdp = signal
+                    .data
+                    .get("sdp")
+                    .and_then(|s| s.as_str())
+                    .unwrap_or("");
+
+                let answer =
+                    webrtc::peer_connection::sdp::session_description::RTCSessionDescription::answer(
+                        sdp.to_string(),
+                    )?;
+
+                if let Some(webrtc) = &self.webrtc {
+                    webrtc.peer.set_remote_description(answer).await?;
+                }
+                println!("[WEBXASH] Set remote description for {}", self.client_id);
+            }
+            events::CANDIDATE => {
+                let candidate: webrtc::ice_transport::ice_candidate::RTCIceCandidateInit =
+                    match serde_json::from_value(signal.data) {
+                        Ok(c) => c,
+                        Err(e) => {
+                            eprintln!(
+                                "[WEBXASH] Invalid ICE candidate from {}: {e}",
+                                self.client_id
+                            );
+                            return Ok(());
+                        }
+                    };
+
+                if let Some(webrtc) = &self.webrtc {
+                    webrtc.peer.add_ice_candidate(candidate).await?;
+                }
+            }
+            _ => {
+                eprintln!(
+                    "[WEBXASH] Unknown signal event from {}: {}",
+                    self.client_id, signal.event
+                );
+            }
+        }
+        Ok(())
+    }
+
+    /// Negotiate the transport and data channels requested by a client `hello`.
+    async fn on_hello(&mut self, hello: ClientHello) -> SignalingResult<()> {
+        // There is no way back from the WebSocket transport
+        if self.websocket.is_none() && hello.transport.as_deref() == Some(transports::WEBSOCKET) {
+            let transport = WebSocketTransport::new(self.ws_sender.clone());
+            self.websocket = Some(transport.clone());
+            send_hello(
+                &self.ws_sender,
+                transports::WEBSOCKET,
+                self.reliability,
+                self.layout,
+            )
+            .await?;
+            self.switch_to_websocket(transport).await;
+            return Ok(());
+        }
+
+        // Data channels need a peer connection
+        if self.webrtc.is_none() && self.websocket.is_none() {
+            return self.send_error(WEBRTC_UNAVAILABLE).await;
+        }
+
+        let reliability = hello
+            .reliability
+            .as_deref()
+            .and_then(ChannelReliability::parse)
+            .unwrap_or(self.reliability);
+        let layout = hello
+            .channels
+            .as_deref()
+            .and_then(ChannelLayout::parse)
+            .unwrap_or(self.layout);
+        if (reliability, layout) != (self.reliability, self.layout) {
+            self.replace_channels(reliability, layout).await?;
+        }
+
+        let transport = if self.websocket.is_some() {
+            transports::WEBSOCKET
+        } else {
+            transports::WEBRTC
+        };
+        send_hello(&self.ws_sender, transport, self.reliability, self.layout).await
+    }
+
+    /// Recreate the data channels with another reliability or layout.
+    ///
+    /// Channels can be replaced until the SCTP association opens them.
+    async fn replace_channels(
+        &mut self,
+        reliability: ChannelReliability,
+        layout: ChannelLayout,
+    ) -> SignalingResult<()> {
+        let Some(webrtc) = &mut self.webrtc else {
+            return Ok(());
+        };
+        if webrtc.write_channel.ready_state() != RTCDataChannelState::Connecting
+            || webrtc.read_channel.ready_state() != RTCDataChannelState::Connecting
+        {
+            return Ok(());
+        }
+
+        let _ = webrtc.write_channel.close().await;
+        let _ = webrtc.read_channel.close().await;
+        (webrtc.write_channel, webrtc.read_channel) =
+            create_channels(&webrtc.peer, reliability, layout).await?;
+        self.bridge_when_open();
+        self.reliability = reliability;
+        self.layout = layout;
+        Ok(())
+    }
+
+    /// Replace the peer connection (and its bridge, if any) with a bridge over
+    /// binary WebSocket frames.
+    async fn switch_to_websocket(&mut self, transport: Arc<WebSocketTransport>) {
+        println!(
+            "[WEBXASH] {} switched to the WebSocket transport",
+            self.client_id
+        );
+
+        {
+            let mut slot = self.bridge.lock().await;
+            slot.switched = true;
+            if let Some(b) = slot.bridge.take() {
+                b.shutdown();
+            }
+        }
+        if let Some(webrtc) = self.webrtc.take() {
+            webrtc.close(&self.client_id).await;
+        }
+
+        start_bridge(
+            self.bridge.clone(),
+            self.config.clone(),
+            self.client_id.clone(),
+            transport,
+        )
+        .await;
+    }
+
+    /// Start the bridge once the write and read channels are open.
+    ///
+    /// A single pre-negotiated channel is both, so one `open` event is enough.
+    fn bridge_when_open(&self) {
+        let Some(webrtc) = &self.webrtc else {
+            return;
+        };
+        let single = Arc::ptr_eq(&webrtc.write_channel, &webrtc.read_channel);
+        let (channels, needed) = if single {
+            (vec![&webrtc.write_channel], 1)
+        } else {
+            (vec![&webrtc.write_channel, &webrtc.read_channel], 2)
+        };
+
+        for channel in channels {
+            let channels_open = webrtc.channels_open.clone();
+            let bridge = self.bridge.clone();
+            let config = self.config.clone();
+            let client_id = self.client_id.clone();
+            let write_channel = webrtc.write_channel.clone();
+            let read_channel = webrtc.read_channel.clone();
+
+            channel.on_open(Box::new(move || {
+                let channels_open = channels_open.clone();
+                let bridge = bridge.clone();
+                let config = config.clone();
+                let client_id = client_id.clone();
+                let write_channel = write_channel.clone();
+                let read_channel = read_channel.clone();
+
+                Box::pin(async move {
+                    let count = channels_open.fetch_add(1, Ordering::SeqCst) + 1;
+                    if count == needed {
+                        let transport =
+                            Arc::new(DataChannelTransport::new(write_channel, read_channel));
+                        start_bridge(bridge, config, client_id, transport).await;
+                    }
+                })
+            }));
+        }
+    }
 
-    println!("[WEBXASH] Sent offer to {client_id}");
+    /// Send an `error` event.
+    async fn send_error(&self, message: &str) -> SignalingResult<()> {
+        let msg = SignalMessage {
+            event: events::ERROR.to_string(),
+            data: serde_json::json!({ "message": message }),
+        };
+        self.ws_sender
+            .lock()
+            .await
+            .send(Message::Text(serde_json::to_string(&msg)?))
+            .await?;
+        Ok(())
+    }
 
-    // Set up ICE candidate handler
-    let ws_sender_arc = Arc::new(tokio::sync::Mutex::new(ws_sender));
-    let ws_sender_for_ice = ws_sender_arc.clone();
-    let client_id_for_ice = client_id.clone();
+    /// Stop the bridge and close the peer connection.
+    async fn close(self) {
+        if let Some(transport) = &self.websocket {
+            transport.close();
+        }
+        if let Some(b) = self.bridge.lock().await.bridge.take() {
+            b.shutdown();
+        }
+        if let Some(webrtc) = self.webrtc {
+            webrtc.close(&self.client_id).await;
+        }
+    }
+}
+
+/// Send local ICE candidates to the client as they are gathered.
+fn send_ice_candidates(
+    peer: &RTCPeerConnection,
+    ws_sender: &Arc<tokio::sync::Mutex<WsSender>>,
+    client_id: &str,
+) {
+    let ws_sender = ws_sender.clone();
+    let client_id = client_id.to_string();
 
     peer.on_ice_candidate(Box::new(move |candidate| {
-        let ws_sender = ws_sender_for_ice.clone();
-        let client_id = client_id_for_ice.clone();
+        let ws_sender = ws_sender.clone();
+        let client_id = client_id.clone();
 
         Box::pin(async move {
             let Some(c) = candidate else {
@@ -111,154 +592,70 @@ async fn handle_signaling(
             }
         })
     }));
+}
 
-    // Set up bridge when channels are ready
-    let bridge = Arc::new(tokio::sync::Mutex::new(Option::<Arc<Bridge>>::None));
-    let bridge_for_callback = bridge.clone();
-    let config_for_bridge = config.clone();
-    let client_id_for_bridge = client_id.clone();
-    let write_channel_for_bridge = write_channel.clone();
-    let read_channel_for_bridge = read_channel.clone();
-
-    // Track channel open states
-    let channels_open = Arc::new(std::sync::atomic::AtomicU8::new(0));
-    let channels_open_write = channels_open.clone();
-    let channels_open_read = channels_open.clone();
-
-    // Clone for both callbacks
-    let bridge_for_write = bridge_for_callback.clone();
-    let config_for_write = config_for_bridge.clone();
-    let client_id_for_write = client_id_for_bridge.clone();
-    let write_channel_for_write = write_channel_for_bridge.clone();
-    let read_channel_for_write = read_channel_for_bridge.clone();
-
-    write_channel.on_open(Box::new(move || {
-        let channels_open = channels_open_write.clone();
-        let bridge = bridge_for_write.clone();
-        let config = config_for_write.clone();
-        let client_id = client_id_for_write.clone();
-        let write_channel = write_channel_for_write.clone();
-        let read_channel = read_channel_for_write.clone();
-
-        Box::pin(async move {
-            let count = channels_open.fetch_add(1, std::sync::atomic::Ordering::SeqCst) + 1;
-            if count == 2 {
-                start_bridge(bridge, config, client_id, write_channel, read_channel).await;
-            }
-        })
-    }));
-
-    let bridge_for_read = bridge_for_callback;
-    let config_for_read = config_for_bridge;
-    let client_id_for_read = client_id_for_bridge;
-    let write_channel_for_read = write_channel_for_bridge;
-    let read_channel_for_read = read_channel_for_bridge;
-
-    read_channel.on_open(Box::new(move || {
-        let channels_open = channels_open_read.clone();
-        let bridge = bridge_for_read.clone();
-        let config = config_for_read.clone();
-        let client_id = client_id_for_read.clone();
-        let write_channel = write_channel_for_read.clone();
-        let read_channel = read_channel_for_read.clone();
-
-        Box::pin(async move {
-            let count = channels_open.fetch_add(1, std::sync::atomic::Ordering::SeqCst) + 1;
-            if count == 2 {
-                start_bridge(bridge, config, client_id, write_channel, read_channel).await;
-            }
-        })
-    }));
-
-    // Handle incoming WebSocket messages
-    while let Some(msg) = ws_receiver.next().await {
-        match msg {
-            Ok(Message::Text(text)) => {
-                let signal: SignalMessage = match serde_json::from_str(&text) {
-                    Ok(s) => s,
-                    Err(e) => {
-                        eprintln!("[WEBXASH] Invalid signal message from {client_id}: {e}");
-                        continue;
-                    }
-                };
-
-                match signal.event.as_str() {
-                    events::ANSWER => {
-                        let sdp = signal
-                            .data
-                            .get("sdp")
-                            .and_then(|s| s.as_str())
-                            .unwrap_or("");
-
-                        let answer =
-                            webrtc::peer_connection::sdp::session_description::RTCSessionDescription::answer(
-                                sdp.to_string(),
-                            )?;
-
-                        peer.set_remote_description(answer).await?;
-                        println!("[WEBXASH] Set remote description for {client_id}");
-                    }
-                    events::CANDIDATE => {
-                        let candidate: webrtc::ice_transport::ice_candidate::RTCIceCandidateInit =
-                            match serde_json::from_value(signal.data) {
-                                Ok(c) => c,
-                                Err(e) => {
-                                    eprintln!(
-                                        "[WEBXASH] Invalid ICE candidate from {client_id}: {e}"
-                                    );
-                                    continue;
-                                }
-                            };
-
-                        peer.add_ice_candidate(candidate).await?;
-                    }
-                    _ => {
-                        eprintln!(
-                            "[WEBXASH] Unknown signal event from {client_id}: {}",
-                            signal.event
-                        );
-                    }
-                }
-            }
-            Ok(Message::Close(_)) => {
-                println!("[WEBXASH] WebSocket close from {client_id}");
-                break;
-            }
-            Ok(Message::Ping(_)) => {
-                // Pong is handled automatically
-            }
-            Ok(_) => {}
-            Err(e) => {
-                eprintln!("[WEBXASH] WebSocket error from {client_id}: {e}");
-                break;
-            }
-        }
-    }
-
-    // Cleanup
-    if let Some(b) = bridge.lock().await.take() {
-        b.shutdown();
+/// Answer a client `hello` with the transport and data channels in use.
+async fn send_hello<S>(
+    ws_sender: &tokio::sync::Mutex<S>,
+    transport: &str,
+    reliability: ChannelReliability,
+    layout: ChannelLayout,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>>
+where
+    S: futures::Sink<Message> + Unpin,
+    S::Error: std::error::Error + Send + Sync + 'static,
+{
+    let mut data = serde_json::json!({
+        "transport": transport,
+        "reliability": reliability.name(),
+        "channels": layout.name()
+    });
+    if layout == ChannelLayout::Single {
+        data["channel_id"] = GAME_CHANNEL_ID.into();
     }
 
+    let msg = SignalMessage {
+        event: events::HELLO.to_string(),
+        data,
+    };
+    ws_sender
+        .lock()
+        .await
+        .send(Message::Text(serde_json::to_string(&msg)?))
+        .await?;
     Ok(())
 }
 
-/// Start the UDP bridge.
+/// Start the UDP bridge and store it in the session's slot.
+///
+/// Once the session switched to WebSocket, a data channel bridge that was
+/// still being created is dropped instead of replacing the WebSocket one.
 async fn start_bridge(
-    bridge_holder: Arc<tokio::sync::Mutex<Option<Arc<Bridge>>>>,
+    bridge_holder: SharedBridgeSlot,
     config: Arc<PluginConfig>,
     client_id: String,
-    write_channel: Arc<webrtc::data_channel::RTCDataChannel>,
-    read_channel: Arc<webrtc::data_channel::RTCDataChannel>,
+    transport: Arc<dyn Transport>,
 ) {
-    println!("[WEBXASH] Both channels open, starting bridge for {client_id}");
+    let websocket = transport.name() == transports::WEBSOCKET;
+    println!(
+        "[WEBXASH] {} transport ready, starting bridge for {client_id}",
+        transport.name()
+    );
 
-    let server_addr = format!("127.0.0.1:{}", config.game_port);
+    let server_addr = config.game_server_addr();
 
-    match Bridge::new(write_channel, read_channel, &server_addr, client_id.clone()).await {
+    match Bridge::new(transport, &server_addr, client_id.clone()).await {
         Ok(b) => {
             let b = Arc::new(b);
-            *bridge_holder.lock().await = Some(b.clone());
+            {
+                let mut slot = bridge_holder.lock().await;
+                if slot.switched && !websocket {
+                    return;
+                }
+                if let Some(previous) = slot.bridge.replace(b.clone()) {
+                    previous.shutdown();
+                }
+            }
             tokio::spawn(async move {
                 b.start().await;
             });
@@ -0,0 +1,236 @@
+//! WebTransport listener (HTTP/3 over QUIC).
+//!
+//! Browsers open a session with an extended CONNECT to `<base_path>/wt`. Game
+//! packets then travel as unreliable HTTP/3 datagrams and are bridged to the
+//! game server like data channel traffic, without ICE.
+
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+
+use bytes::{Buf, Bytes};
+use http::{Method, Request, Response, StatusCode};
+use once_cell::sync::Lazy;
+use parking_lot::RwLock;
+use quinn::crypto::rustls::QuicServerConfig;
+
+use crate::bridge::{Bridge, DatagramTransport, DATAGRAM_BUFFER_SIZE};
+use crate::config::PluginConfig;
+use crate::metrics;
+use crate::server::cors::cors_headers;
+use crate::server::http::uuid_simple;
+use crate::server::request::strip_base_path;
+use crate::server::tls::{TlsIdentity, SELF_SIGNED_VALIDITY};
+use crate::session::SessionGuard;
+
+/// Path of WebTransport sessions, below the base path
+pub const SESSION_PATH: &str = "/wt";
+
+/// ALPN protocol of HTTP/3
+const ALPN_H3: &[u8] = b"h3";
+
+/// Connections without any traffic for this long are closed
+const IDLE_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Interval of QUIC keepalives (well below the idle timeout)
+const KEEP_ALIVE_INTERVAL: Duration = Duration::from_secs(10);
+
+/// How long before expiry a self-signed certificate is replaced
+const ROTATE_BEFORE_EXPIRY: Duration = Duration::from_hours(24);
+
+/// Hex SHA-256 of the current self-signed certificate
+static CERTIFICATE_HASH: Lazy<RwLock<Option<String>>> = Lazy::new(|| RwLock::new(None));
+
+type BoxError = Box<dyn std::error::Error + Send + Sync>;
+type H3Connection = h3::server::Connection<h3_quinn::Connection, Bytes>;
+type H3Stream = h3::server::RequestStream<h3_quinn::BidiStream<Bytes>, Bytes>;
+
+/// Hash browsers must pin (`serverCertificateHashes`) while the certificate is self-signed.
+pub fn certificate_hash() -> Option<String> {
+    CERTIFICATE_HASH.read().clone()
+}
+
+/// Accept WebTransport sessions on the configured UDP port.
+pub async fn run(config: Arc<PluginConfig>) -> Result<(), BoxError> {
+    let Some(port) = config.webtransport_port else {
+        return Ok(());
+    };
+
+    let identity = TlsIdentity::load(&config)?;
+    let self_signed = identity.self_signed_hash.is_some();
+    let endpoint = quinn::Endpoint::server(
+        server_config(identity)?,
+        SocketAddr::from(([0, 0, 0, 0], port)),
+    )?;
+
+    println!("[WEBXASH] WebTransport listening on udp/{port}");
+
+    if self_signed {
+        tokio::spawn(rotate_certificate(endpoint.clone(), config.clone()));
+    }
+
+    while let Some(incoming) = endpoint.accept().await {
+        let config = config.clone();
+        tokio::spawn(async move {
+            let remote = incoming.remote_address();
+            if let Err(e) = handle_connection(incoming, config).await {
+                eprintln!("[WEBXASH] WebTransport error from {remote}: {e}");
+            }
+        });
+    }
+
+    Ok(())
+}
+
+/// Build the QUIC server configuration and publish the certificate hash.
+fn server_config(identity: TlsIdentity) -> Result<quinn::ServerConfig, BoxError> {
+    let mut tls = rustls::ServerConfig::builder_with_provider(Arc::new(
+        rustls::crypto::ring::default_provider(),
+    ))
+    .with_protocol_versions(&[&rustls::version::TLS13])?
+    .with_no_client_auth()
+    .with_single_cert(identity.chain, identity.key)?;
+    tls.alpn_protocols = vec![ALPN_H3.to_vec()];
+
+    let mut transport = quinn::TransportConfig::default();
+    transport
+        .max_idle_timeout(Some(IDLE_TIMEOUT.try_into()?))
+        .keep_alive_interval(Some(KEEP_ALIVE_INTERVAL))
+        .datagram_receive_buffer_size(Some(DATAGRAM_BUFFER_SIZE))
+        .datagram_send_buffer_size(DATAGRAM_BUFFER_SIZE);
+
+    let mut server = quinn::ServerConfig::with_crypto(Arc::new(QuicServerConfig::try_from(tls)?));
+    server.transport_config(Arc::new(transport));
+
+    *CERTIFICATE_HASH.write() = identity.self_signed_hash;
+    Ok(server)
+}
+
+/// Replace the self-signed certificate before it expires.
+///
+/// Established sessions keep running; new sessions need the new hash from `/info`.
+async fn rotate_certificate(endpoint: quinn::Endpoint, config: Arc<PluginConfig>) {
+    loop {
+        tokio::time::sleep(SELF_SIGNED_VALIDITY.saturating_sub(ROTATE_BEFORE_EXPIRY)).await;
+
+        match TlsIdentity::load(&config).and_then(server_config) {
+            Ok(server_config) => {
+                endpoint.set_server_config(Some(server_config));
+                println!("[WEBXASH] Rotated the WebTransport certificate");
+            }
+            Err(e) => eprintln!("[WEBXASH] Failed to rotate the WebTransport certificate: {e}"),
+        }
+    }
+}
+
+/// Serve one QUIC connection: accept a session request, then bridge its datagrams.
+async fn handle_connection(
+    incoming: quinn::Incoming,
+    config: Arc<PluginConfig>,
+) -> Result<(), BoxError> {
+    let connection = incoming.await?;
+    let client_addr = connection.remote_address();
+
+    let mut h3: H3Connection = h3::server::builder()
+        .enable_webtransport(true)
+        .enable_extended_connect(true)
+        .enable_datagram(true)
+        .max_webtransport_sessions(1)
+        .send_grease(true)
+        .build(h3_quinn::Connection::new(connection.clone()))
+        .await?;
+
+    // Refuse requests until one opens a valid session
+    let mut stream = loop {
+        let Some(resolver) = h3.accept().await? else {
+            return Ok(());
+        };
+        let (request, mut stream) = resolver.resolve_request().await?;
+
+        let status = session_status(&request, &config);
+        stream
+            .send_response(Response::builder().status(status).body(())?)
+            .await?;
+        if status == StatusCode::OK {
+            break stream;
+        }
+        stream.finish().await?;
+    };
+
+    let client_id = format!("{client_addr}-{}", uuid_simple());
+    println!("[WEBXASH] New WebTransport session: {client_id}");
+    metrics::WEBTRANSPORT_SESSIONS.inc();
+    let session = SessionGuard::register(client_id.clone(), client_addr);
+
+    let transport = Arc::new(DatagramTransport::new(
+        connection.clone(),
+        stream.id().into_inner(),
+    ));
+    let result = match Bridge::new(transport, &config.game_server_addr(), client_id.clone()).await {
+        Ok(bridge) => {
+            let bridge = Arc::new(bridge);
+            tokio::select! {
+                () = bridge.clone().start() => {}
+                () = session.kicked() => println!("[WEBXASH] Kicking {client_id}"),
+                () = session_closed(&mut stream) => {}
+                () = refuse_requests(&mut h3) => {}
+            }
+            bridge.shutdown();
+            Ok(())
+        }
+        Err(e) => Err(e),
+    };
+
+    connection.close(0u32.into(), b"");
+    metrics::WEBTRANSPORT_SESSIONS.dec();
+    println!("[WEBXASH] WebTransport session closed: {client_id}");
+
+    result
+}
+
+/// Check a session request: returns `200 OK` or the status to refuse it with.
+fn session_status(request: &Request<()>, config: &PluginConfig) -> StatusCode {
+    let is_webtransport = request.method() == Method::CONNECT
+        && request.extensions().get::<h3::ext::Protocol>()
+            == Some(&h3::ext::Protocol::WEB_TRANSPORT);
+    if !is_webtransport {
+        return StatusCode::BAD_REQUEST;
+    }
+
+    // Same origin allowlist as HTTP and `/ws`
+    let origin = request
+        .headers()
+        .get(http::header::ORIGIN)
+        .and_then(|origin| origin.to_str().ok());
+    if cors_headers(&config.allowed_origins, origin).is_none() {
+        return StatusCode::FORBIDDEN;
+    }
+
+    if strip_base_path(request.uri().path(), &config.base_path) == Some(SESSION_PATH) {
+        StatusCode::OK
+    } else {
+        StatusCode::NOT_FOUND
+    }
+}
+
+/// Wait until the client ends the session (finishes or resets the CONNECT stream).
+async fn session_closed(stream: &mut H3Stream) {
+    // Capsules are not used; skip their data
+    while let Ok(Some(mut data)) = stream.recv_data().await {
+        data.advance(data.remaining());
+    }
+}
+
+/// Answer further requests on the connection until it closes.
+async fn refuse_requests(h3: &mut H3Connection) {
+    while let Ok(Some(resolver)) = h3.accept().await {
+        if let Ok((_, mut stream)) = resolver.resolve_request().await {
+            let response = Response::builder()
+                .status(StatusCode::CONFLICT)
+                .body(())
+                .unwrap_or_default();
+            let _ = stream.send_response(response).await;
+            let _ = stream.finish().await;
+        }
+    }
+}
@@ -0,0 +1,99 @@
+//! Registry of active signaling sessions.
+//!
+//! Each WebSocket session registers itself so the admin API can list and
+//! kick web clients.
+
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use once_cell::sync::Lazy;
+use parking_lot::Mutex;
+use serde::Serialize;
+use tokio::sync::Notify;
+
+/// Active sessions keyed by client id
+static SESSIONS: Lazy<Mutex<HashMap<String, Session>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+struct Session {
+    client_addr: SocketAddr,
+    /// Unix time (ms) the WebSocket connected
+    connected_at: u64,
+    /// Signalled to end the session
+    kick: Arc<Notify>,
+}
+
+/// Public view of a session
+#[derive(Debug, Clone, Serialize)]
+pub struct SessionInfo {
+    pub client_id: String,
+    pub client_addr: SocketAddr,
+    /// Unix time (ms) the WebSocket connected
+    pub connected_at: u64,
+}
+
+/// Registration of one session; unregisters on drop.
+pub struct SessionGuard {
+    client_id: String,
+    kick: Arc<Notify>,
+}
+
+impl SessionGuard {
+    /// Register a session.
+    pub fn register(client_id: String, client_addr: SocketAddr) -> Self {
+        let kick = Arc::new(Notify::new());
+        let connected_at = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| u64::try_from(d.as_millis()).unwrap_or(u64::MAX));
+
+        SESSIONS.lock().insert(
+            client_id.clone(),
+            Session {
+                client_addr,
+                connected_at,
+                kick: kick.clone(),
+            },
+        );
+
+        Self { client_id, kick }
+    }
+
+    /// Wait until the session is kicked.
+    pub async fn kicked(&self) {
+        self.kick.notified().await;
+    }
+}
+
+impl Drop for SessionGuard {
+    fn drop(&mut self) {
+        SESSIONS.lock().remove(&self.client_id);
+    }
+}
+
+/// List active sessions, oldest first.
+pub fn list() -> Vec<SessionInfo> {
+    let mut sessions: Vec<_> = SESSIONS
+        .lock()
+        .iter()
+        .map(|(client_id, session)| SessionInfo {
+            client_id: client_id.clone(),
+            client_addr: session.client_addr,
+            connected_at: session.connected_at,
+        })
+        .collect();
+    sessions.sort_by_key(|session| session.connected_at);
+    sessions
+}
+
+/// Kick a session; returns `false` if no such session exists.
+pub fn kick(client_id: &str) -> bool {
+    match SESSIONS.lock().get(client_id) {
+        Some(session) => {
+            // Stored as a permit, so a kick is not lost if the session is not waiting yet
+            session.kick.notify_one();
+            true
+        }
+        None => false,
+    }
+}
@@ -0,0 +1,209 @@
+//! Embedded TURN server.
+//!
+//! Relays data channel traffic for browsers that cannot reach the server
+//! directly (symmetric NATs, UDP-hostile networks). Listens on UDP and TCP on
+//! the same port, and takes TURN over TCP on the HTTP port too. Accepts TURN
+//! REST credentials derived from a shared secret; signaling sessions get
+//! credentials of their own in the offer.
+
+mod quota;
+mod tcp;
+
+use std::fmt::Write;
+use std::net::IpAddr;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use once_cell::sync::{Lazy, OnceCell};
+use parking_lot::RwLock;
+use tokio::net::{TcpStream, UdpSocket};
+use webrtc::turn::auth::{generate_auth_key, AuthHandler};
+use webrtc::turn::relay::relay_static::RelayAddressGeneratorStatic;
+use webrtc::turn::server::config::{ConnConfig, ServerConfig};
+use webrtc::turn::server::Server;
+use webrtc::util::vnet::net::Net;
+use webrtc::util::Conn;
+
+use crate::config::PluginConfig;
+use crate::webrtc::{rest_credentials, rest_password, IceServer};
+use quota::{QuotaConn, Quotas};
+use tcp::TcpConn;
+
+/// Realm of the long-term credentials
+const REALM: &str = "webxash";
+
+/// Time a connection on the HTTP port has to send its first byte
+const SNIFF_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Interval between sweeps of idle quota entries
+const SWEEP_INTERVAL: Duration = Duration::from_mins(1);
+
+/// Shared secret of the running server
+static SECRET: Lazy<RwLock<Option<String>>> = Lazy::new(|| RwLock::new(None));
+
+/// TCP side of the running server, for connections sniffed on the HTTP port
+static TCP: OnceCell<Arc<TcpConn>> = OnceCell::new();
+
+type BoxError = Box<dyn std::error::Error + Send + Sync>;
+
+/// Run the TURN server on the configured port.
+pub async fn run(config: Arc<PluginConfig>) -> Result<(), BoxError> {
+    let Some(port) = config.turn_port else {
+        return Ok(());
+    };
+    // Relay addresses are handed out as is, so they must be public
+    let Some(relay_address) = relay_address(&config) else {
+        return Err("webxash_turn_port needs webxash_public_ip set to an IP address".into());
+    };
+
+    // Only our own signaling hands out credentials, so any secret will do
+    let secret = config.turn_secret.clone().unwrap_or_else(random_secret);
+
+    let quotas = Arc::new(Quotas::new(
+        config.turn_max_allocations,
+        config.turn_max_rate,
+    ));
+    let udp: Arc<dyn Conn + Send + Sync> = Arc::new(UdpSocket::bind(("0.0.0.0", port)).await?);
+    let tcp = TcpConn::bind(port).await?;
+    let _ = TCP.set(tcp.clone());
+    let tcp: Arc<dyn Conn + Send + Sync> = tcp;
+
+    let net = Arc::new(Net::new(None));
+    let conn_configs = [udp, tcp]
+        .into_iter()
+        .map(|conn| ConnConfig {
+            conn: Arc::new(QuotaConn::new(conn, quotas.clone())),
+            relay_addr_generator: Box::new(RelayAddressGeneratorStatic {
+                relay_address,
+                address: "0.0.0.0".to_string(),
+                net: net.clone(),
+            }),
+        })
+        .collect();
+
+    let server = Arc::new(
+        Server::new(ServerConfig {
+            conn_configs,
+            realm: REALM.to_string(),
+            auth_handler: Arc::new(RestAuthHandler {
+                secret: secret.clone(),
+            }),
+            channel_bind_timeout: Duration::ZERO,
+            alloc_close_notify: None,
+        })
+        .await?,
+    );
+    quotas.attach(&server);
+    *SECRET.write() = Some(secret);
+
+    println!(
+        "[WEBXASH] TURN server listening on udp/{port} and tcp/{port} (relay {relay_address})"
+    );
+
+    // The server stops when dropped
+    loop {
+        tokio::time::sleep(SWEEP_INTERVAL).await;
+        quotas.sweep();
+    }
+}
+
+/// The embedded server as an ICE server for one signaling session.
+///
+/// `user` names the session in the TURN username. `None` unless the server is running.
+pub fn ice_server(config: &PluginConfig, user: &str) -> Option<IceServer> {
+    let secret = SECRET.read().clone()?;
+    let port = config.turn_port?;
+    let host = match relay_address(config)? {
+        IpAddr::V4(ip) => ip.to_string(),
+        IpAddr::V6(ip) => format!("[{ip}]"),
+    };
+
+    let expiry = SystemTime::now().checked_add(config.turn_credential_ttl)?;
+    let (username, credential) = rest_credentials(&secret, user, expiry)?;
+
+    let mut urls = vec![
+        format!("turn:{host}:{port}?transport=udp"),
+        format!("turn:{host}:{port}?transport=tcp"),
+    ];
+    // For networks that only let the game port through
+    if config.http_port != port {
+        urls.push(format!("turn:{host}:{}?transport=tcp", config.http_port));
+    }
+
+    Some(IceServer {
+        urls,
+        username: Some(username),
+        credential: Some(credential),
+    })
+}
+
+/// Check whether a connection on the HTTP port speaks TURN.
+///
+/// STUN messages and `ChannelData` start with a byte below `0x40`, HTTP
+/// requests with a letter. Silent connections are left to the HTTP side.
+pub async fn is_turn_stream(stream: &TcpStream) -> bool {
+    if TCP.get().is_none() {
+        return false;
+    }
+
+    let mut first = [0u8; 1];
+    matches!(
+        tokio::time::timeout(SNIFF_TIMEOUT, stream.peek(&mut first)).await,
+        Ok(Ok(1)) if first[0] < 0x40
+    )
+}
+
+/// Hand a TURN connection from the HTTP port to the server.
+pub fn accept_tcp(stream: TcpStream, addr: std::net::SocketAddr) {
+    if let Some(tcp) = TCP.get() {
+        tcp.accept(stream, addr);
+    }
+}
+
+/// Generate a random hex secret.
+fn random_secret() -> String {
+    rand::random::<[u8; 32]>()
+        .iter()
+        .fold(String::new(), |mut hex, byte| {
+            let _ = write!(hex, "{byte:02x}");
+            hex
+        })
+}
+
+/// Public address relays are reachable at.
+fn relay_address(config: &PluginConfig) -> Option<IpAddr> {
+    config.public_ip.as_deref()?.parse().ok()
+}
+
+/// Checks TURN REST usernames (`<expiry unix time>:<user>`) against the shared secret
+struct RestAuthHandler {
+    secret: String,
+}
+
+impl AuthHandler for RestAuthHandler {
+    fn auth_handle(
+        &self,
+        username: &str,
+        realm: &str,
+        _src_addr: std::net::SocketAddr,
+    ) -> Result<Vec<u8>, webrtc::turn::Error> {
+        let expiry = username
+            .split_once(':')
+            .map_or(username, |(expiry, _)| expiry)
+            .parse::<u64>()
+            .map_err(|_| webrtc::turn::Error::Other(format!("Invalid username {username}")))?;
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or_default();
+        if expiry < now {
+            return Err(webrtc::turn::Error::Other(format!(
+                "Expired username {username}"
+            )));
+        }
+
+        let password = rest_password(&self.secret, username)
+            .ok_or_else(|| webrtc::turn::Error::Other("Invalid secret".to_string()))?;
+        Ok(generate_auth_key(username, realm, &password))
+    }
+}
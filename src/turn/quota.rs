@@ -0,0 +1,281 @@
+//! Per-client relay quotas of the TURN server.
+//!
+//! A client is a TURN username; with REST credentials that is one signaling
+//! session. Quotas are enforced in front of the server's listeners: allocate
+//! requests over the allocation limit are refused with `486 Allocation Quota
+//! Reached`, and relayed data over the rate limit is dropped.
+
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::{Arc, Weak};
+use std::time::{Duration, Instant};
+
+use async_trait::async_trait;
+use once_cell::sync::OnceCell;
+use parking_lot::Mutex;
+use webrtc::stun::attributes::ATTR_USERNAME;
+use webrtc::stun::error_code::CODE_ALLOC_QUOTA_REACHED;
+use webrtc::stun::message::{
+    is_message, Message, MessageType, CLASS_ERROR_RESPONSE, CLASS_REQUEST, METHOD_ALLOCATE,
+};
+use webrtc::stun::textattrs::TextAttribute;
+use webrtc::turn::server::Server;
+use webrtc::util::{Conn, Error};
+
+/// Quota entries unused for this long are forgotten
+const IDLE_TIMEOUT: Duration = Duration::from_mins(10);
+
+/// Raw STUN message types of Send and Data indications
+const SEND_INDICATION: u16 = 0x0016;
+const DATA_INDICATION: u16 = 0x0017;
+
+/// Allocation and bandwidth limits shared by all listeners
+pub struct Quotas {
+    /// Concurrent allocations per username, 0 for no limit
+    max_allocations: usize,
+    /// Relayed bytes per second per username, 0 for no limit
+    max_rate: u64,
+    /// The server whose allocations are counted
+    server: OnceCell<Weak<Server>>,
+    state: Mutex<State>,
+}
+
+#[derive(Default)]
+struct State {
+    /// Username and last activity of each client transport address
+    clients: HashMap<SocketAddr, (String, Instant)>,
+    /// Relay bandwidth left per username
+    buckets: HashMap<String, ByteBucket>,
+}
+
+impl Quotas {
+    pub fn new(max_allocations: usize, max_rate: u64) -> Self {
+        Self {
+            max_allocations,
+            max_rate,
+            server: OnceCell::new(),
+            state: Mutex::new(State::default()),
+        }
+    }
+
+    /// Count allocations on `server`.
+    pub fn attach(&self, server: &Arc<Server>) {
+        let _ = self.server.set(Arc::downgrade(server));
+    }
+
+    /// Forget clients and buckets that have been idle for a while.
+    pub fn sweep(&self) {
+        let now = Instant::now();
+        let mut state = self.state.lock();
+        state
+            .clients
+            .retain(|_, (_, seen)| now.duration_since(*seen) < IDLE_TIMEOUT);
+        state
+            .buckets
+            .retain(|_, bucket| now.duration_since(bucket.updated) < IDLE_TIMEOUT);
+    }
+
+    /// Remember which username a transport address authenticates as.
+    fn bind(&self, addr: SocketAddr, username: &str) {
+        self.state
+            .lock()
+            .clients
+            .insert(addr, (username.to_string(), Instant::now()));
+    }
+
+    /// Check whether `username` may create another allocation.
+    async fn may_allocate(&self, username: &str) -> bool {
+        if self.max_allocations == 0 {
+            return true;
+        }
+        let Some(server) = self.server.get().and_then(Weak::upgrade) else {
+            return true;
+        };
+
+        match server.get_allocations_info(None).await {
+            Ok(allocations) => {
+                allocations
+                    .values()
+                    .filter(|info| info.username == username)
+                    .count()
+                    < self.max_allocations
+            }
+            Err(_) => true,
+        }
+    }
+
+    /// Take `bytes` of relay bandwidth for the client at `addr`.
+    ///
+    /// Addresses that never authenticated cannot relay anything, so they pass.
+    fn allow(&self, addr: SocketAddr, bytes: usize) -> bool {
+        if self.max_rate == 0 {
+            return true;
+        }
+
+        let now = Instant::now();
+        let mut state = self.state.lock();
+        let State { clients, buckets } = &mut *state;
+        let Some((username, seen)) = clients.get_mut(&addr) else {
+            return true;
+        };
+        *seen = now;
+
+        buckets
+            .entry(username.clone())
+            .or_insert_with(|| ByteBucket::new(self.max_rate))
+            .try_take(now, u64::try_from(bytes).unwrap_or(u64::MAX), self.max_rate)
+    }
+}
+
+/// A listener with quotas applied
+pub struct QuotaConn {
+    inner: Arc<dyn Conn + Send + Sync>,
+    quotas: Arc<Quotas>,
+}
+
+impl QuotaConn {
+    pub fn new(inner: Arc<dyn Conn + Send + Sync>, quotas: Arc<Quotas>) -> Self {
+        Self { inner, quotas }
+    }
+}
+
+#[async_trait]
+impl Conn for QuotaConn {
+    async fn connect(&self, addr: SocketAddr) -> Result<(), Error> {
+        self.inner.connect(addr).await
+    }
+
+    async fn recv(&self, buf: &mut [u8]) -> Result<usize, Error> {
+        self.inner.recv(buf).await
+    }
+
+    async fn recv_from(&self, buf: &mut [u8]) -> Result<(usize, SocketAddr), Error> {
+        loop {
+            let (n, addr) = self.inner.recv_from(buf).await?;
+            let packet = &buf[..n];
+
+            if is_relayed(packet) {
+                if self.quotas.allow(addr, n) {
+                    return Ok((n, addr));
+                }
+                continue;
+            }
+
+            if let Some((request, username)) = allocate_request(packet) {
+                self.quotas.bind(addr, &username);
+                if !self.quotas.may_allocate(&username).await {
+                    if let Some(response) = quota_reached(&request) {
+                        let _ = self.inner.send_to(&response, addr).await;
+                    }
+                    continue;
+                }
+            }
+
+            return Ok((n, addr));
+        }
+    }
+
+    async fn send(&self, buf: &[u8]) -> Result<usize, Error> {
+        self.inner.send(buf).await
+    }
+
+    async fn send_to(&self, buf: &[u8], target: SocketAddr) -> Result<usize, Error> {
+        // Dropped like a lost datagram
+        if is_relayed(buf) && !self.quotas.allow(target, buf.len()) {
+            return Ok(buf.len());
+        }
+        self.inner.send_to(buf, target).await
+    }
+
+    fn local_addr(&self) -> Result<SocketAddr, Error> {
+        self.inner.local_addr()
+    }
+
+    fn remote_addr(&self) -> Option<SocketAddr> {
+        self.inner.remote_addr()
+    }
+
+    async fn close(&self) -> Result<(), Error> {
+        self.inner.close().await
+    }
+
+    fn as_any(&self) -> &(dyn std::any::Any + Send + Sync) {
+        self
+    }
+}
+
+/// Check whether a packet carries relayed data (`ChannelData`, Send or Data indication).
+fn is_relayed(packet: &[u8]) -> bool {
+    match packet {
+        [0x40..=0x7f, ..] => true,
+        [high, low, ..] => matches!(
+            u16::from_be_bytes([*high, *low]),
+            SEND_INDICATION | DATA_INDICATION
+        ),
+        _ => false,
+    }
+}
+
+/// Decode an allocate request carrying a username.
+fn allocate_request(packet: &[u8]) -> Option<(Message, String)> {
+    if !is_message(packet) {
+        return None;
+    }
+
+    let mut request = Message::new();
+    request.raw = packet.to_vec();
+    request.decode().ok()?;
+    if request.typ != MessageType::new(METHOD_ALLOCATE, CLASS_REQUEST) {
+        return None;
+    }
+
+    let username = TextAttribute::get_from_as(&request, ATTR_USERNAME).ok()?;
+    Some((request, username.text))
+}
+
+/// Build the `486 Allocation Quota Reached` response to an allocate request.
+fn quota_reached(request: &Message) -> Option<Vec<u8>> {
+    let mut response = Message::new();
+    response
+        .build(&[
+            Box::new(request.clone()),
+            Box::new(MessageType::new(METHOD_ALLOCATE, CLASS_ERROR_RESPONSE)),
+            Box::new(CODE_ALLOC_QUOTA_REACHED),
+        ])
+        .ok()?;
+    Some(response.raw)
+}
+
+/// Relay bandwidth of one client, in bytes
+struct ByteBucket {
+    bytes: u64,
+    updated: Instant,
+}
+
+impl ByteBucket {
+    fn new(burst: u64) -> Self {
+        Self {
+            bytes: burst,
+            updated: Instant::now(),
+        }
+    }
+
+    /// Take `bytes`, refilling at `rate` bytes per second up to one second's worth.
+    fn try_take(&mut self, now: Instant, bytes: u64, rate: u64) -> bool {
+        let elapsed = now.duration_since(self.updated);
+        let refill = u128::from(rate) * elapsed.as_micros() / 1_000_000;
+        // Keep the fraction of a byte for the next call
+        if refill > 0 {
+            let total = u128::from(self.bytes) + refill;
+            self.bytes = u64::try_from(total).unwrap_or(u64::MAX).min(rate);
+            self.updated = now;
+        }
+
+        if self.bytes >= bytes {
+            self.bytes -= bytes;
+            true
+        } else {
+            false
+        }
+    }
+}
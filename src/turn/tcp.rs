@@ -0,0 +1,199 @@
+//! TURN over TCP (RFC 6062 framing) as a packet connection.
+//!
+//! The TURN server reads packets from one `Conn`; this one takes TCP
+//! connections (from its own listener or sniffed on the HTTP port), splits
+//! their streams into STUN messages and `ChannelData` frames and tags each
+//! with the connection's remote address.
+
+use std::collections::HashMap;
+use std::io;
+use std::net::SocketAddr;
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use parking_lot::Mutex;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::tcp::OwnedReadHalf;
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::mpsc;
+use webrtc::util::{Conn, Error};
+
+/// Size of a STUN message header
+const STUN_HEADER_SIZE: usize = 20;
+
+/// Frames queued from all connections for the server
+const INCOMING_QUEUE: usize = 1024;
+
+/// Frames queued for one connection before further ones are dropped
+const OUTGOING_QUEUE: usize = 256;
+
+/// Writers of the open connections
+type Writers = Arc<Mutex<HashMap<SocketAddr, mpsc::Sender<Vec<u8>>>>>;
+
+/// All TCP connections of the TURN server, as one packet connection
+pub struct TcpConn {
+    local_addr: SocketAddr,
+    incoming_tx: mpsc::Sender<(Vec<u8>, SocketAddr)>,
+    incoming: tokio::sync::Mutex<mpsc::Receiver<(Vec<u8>, SocketAddr)>>,
+    writers: Writers,
+}
+
+impl TcpConn {
+    /// Listen on `port` and start accepting connections.
+    pub async fn bind(port: u16) -> io::Result<Arc<Self>> {
+        let listener = TcpListener::bind(("0.0.0.0", port)).await?;
+        let (incoming_tx, incoming) = mpsc::channel(INCOMING_QUEUE);
+
+        let conn = Arc::new(Self {
+            local_addr: listener.local_addr()?,
+            incoming_tx,
+            incoming: tokio::sync::Mutex::new(incoming),
+            writers: Writers::default(),
+        });
+
+        let weak = Arc::downgrade(&conn);
+        tokio::spawn(async move {
+            loop {
+                match listener.accept().await {
+                    Ok((stream, addr)) => match weak.upgrade() {
+                        Some(conn) => conn.accept(stream, addr),
+                        None => break,
+                    },
+                    Err(e) => eprintln!("[WEBXASH] TURN TCP accept error: {e}"),
+                }
+            }
+        });
+
+        Ok(conn)
+    }
+
+    /// Serve a connection accepted elsewhere; `addr` is the client address.
+    pub fn accept(&self, stream: TcpStream, addr: SocketAddr) {
+        tokio::spawn(serve(
+            stream,
+            addr,
+            self.incoming_tx.clone(),
+            self.writers.clone(),
+        ));
+    }
+}
+
+/// Pass frames between one TCP connection and the server until it closes.
+async fn serve(
+    stream: TcpStream,
+    addr: SocketAddr,
+    incoming: mpsc::Sender<(Vec<u8>, SocketAddr)>,
+    writers: Writers,
+) {
+    let _ = stream.set_nodelay(true);
+    let (mut reader, mut writer) = stream.into_split();
+
+    let (outgoing, mut queue) = mpsc::channel::<Vec<u8>>(OUTGOING_QUEUE);
+    writers.lock().insert(addr, outgoing);
+    let write_task = tokio::spawn(async move {
+        while let Some(frame) = queue.recv().await {
+            if writer.write_all(&frame).await.is_err() {
+                break;
+            }
+        }
+    });
+
+    while let Ok(frame) = read_frame(&mut reader).await {
+        if incoming.send((frame, addr)).await.is_err() {
+            break;
+        }
+    }
+
+    writers.lock().remove(&addr);
+    write_task.abort();
+}
+
+/// Read one STUN message or `ChannelData` frame (without its padding).
+async fn read_frame(reader: &mut OwnedReadHalf) -> io::Result<Vec<u8>> {
+    let mut header = [0u8; 4];
+    reader.read_exact(&mut header).await?;
+    let length = usize::from(u16::from_be_bytes([header[2], header[3]]));
+
+    // The two top bits tell STUN (0b00) and ChannelData (0b01) apart
+    let (body, padded) = match header[0] >> 6 {
+        0 => {
+            let body = STUN_HEADER_SIZE - header.len() + length;
+            (body, body)
+        }
+        1 => (length, length.next_multiple_of(4)),
+        _ => {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "not a TURN frame",
+            ))
+        }
+    };
+
+    let mut frame = vec![0; header.len() + padded];
+    frame[..header.len()].copy_from_slice(&header);
+    reader.read_exact(&mut frame[header.len()..]).await?;
+    frame.truncate(header.len() + body);
+    Ok(frame)
+}
+
+#[async_trait]
+impl Conn for TcpConn {
+    async fn connect(&self, _addr: SocketAddr) -> Result<(), Error> {
+        Err(Error::Other("not applicable".to_string()))
+    }
+
+    async fn recv(&self, _buf: &mut [u8]) -> Result<usize, Error> {
+        Err(Error::Other("not applicable".to_string()))
+    }
+
+    async fn recv_from(&self, buf: &mut [u8]) -> Result<(usize, SocketAddr), Error> {
+        let (frame, addr) = self
+            .incoming
+            .lock()
+            .await
+            .recv()
+            .await
+            .ok_or(Error::ErrUseClosedNetworkConn)?;
+
+        let n = frame.len().min(buf.len());
+        buf[..n].copy_from_slice(&frame[..n]);
+        Ok((n, addr))
+    }
+
+    async fn send(&self, _buf: &[u8]) -> Result<usize, Error> {
+        Err(Error::Other("not applicable".to_string()))
+    }
+
+    async fn send_to(&self, buf: &[u8], target: SocketAddr) -> Result<usize, Error> {
+        let Some(writer) = self.writers.lock().get(&target).cloned() else {
+            return Err(Error::Other(format!("No TCP connection from {target}")));
+        };
+
+        // ChannelData is padded to four bytes on streams
+        let mut frame = buf.to_vec();
+        if matches!(frame.first(), Some(0x40..=0x7f)) {
+            frame.resize(frame.len().next_multiple_of(4), 0);
+        }
+
+        // A connection that does not keep up loses frames, like UDP would
+        let _ = writer.try_send(frame);
+        Ok(buf.len())
+    }
+
+    fn local_addr(&self) -> Result<SocketAddr, Error> {
+        Ok(self.local_addr)
+    }
+
+    fn remote_addr(&self) -> Option<SocketAddr> {
+        None
+    }
+
+    async fn close(&self) -> Result<(), Error> {
+        self.incoming.lock().await.close();
+        Ok(())
+    }
+
+    fn as_any(&self) -> &(dyn std::any::Any + Send + Sync) {
+        self
+    }
+}
@@ -0,0 +1,131 @@
+//! DTLS certificate of the server's peer connections.
+//!
+//! Generating a certificate costs a key pair and a signature, so one is kept
+//! on disk and shared by every peer connection instead of made per client.
+//! Its fingerprint is published through `/info`, so clients that fetched it
+//! over a trusted channel can compare it with the one in the offer.
+
+use std::path::Path;
+use std::time::{Duration, SystemTime};
+
+use once_cell::sync::Lazy;
+use parking_lot::RwLock;
+use serde::Serialize;
+use webrtc::peer_connection::certificate::RTCCertificate;
+
+/// Where the certificate and its private key are kept (PEM, with its expiry)
+pub const CERTIFICATE_PATH: &str = "cstrike/addons/webxash/dtls_cert.pem";
+
+/// Fingerprint of the certificate new peer connections use
+static FINGERPRINT: Lazy<RwLock<Option<Fingerprint>>> = Lazy::new(|| RwLock::new(None));
+
+type BoxError = Box<dyn std::error::Error + Send + Sync>;
+
+/// Certificate fingerprint, as in the SDP `a=fingerprint` attribute
+#[derive(Debug, Clone, Serialize)]
+pub struct Fingerprint {
+    /// Hash function, `sha-256`
+    pub algorithm: String,
+    /// Colon-separated hex digest
+    pub value: String,
+}
+
+/// A certificate and when it was generated
+pub struct StoredCertificate {
+    pub certificate: RTCCertificate,
+    pub created: SystemTime,
+}
+
+impl StoredCertificate {
+    /// When the certificate is due for replacement after `max_age`; `None`
+    /// if that is too far in the future to represent (never).
+    pub fn rotation_due(&self, max_age: Duration) -> Option<SystemTime> {
+        self.created.checked_add(max_age)
+    }
+
+    /// Make this the certificate announced in `/info`.
+    pub fn publish(&self) {
+        let fingerprint =
+            self.certificate
+                .get_fingerprints()
+                .into_iter()
+                .next()
+                .map(|fingerprint| Fingerprint {
+                    algorithm: fingerprint.algorithm,
+                    value: fingerprint.value,
+                });
+        *FINGERPRINT.write() = fingerprint;
+    }
+}
+
+/// Fingerprint of the current certificate, once peer connections are set up.
+pub fn fingerprint() -> Option<Fingerprint> {
+    FINGERPRINT.read().clone()
+}
+
+/// Load the stored certificate, or generate and store a new one.
+///
+/// A stored certificate older than `max_age` is replaced.
+pub async fn load_or_generate(max_age: Option<Duration>) -> Result<StoredCertificate, BoxError> {
+    match load().await {
+        Ok(Some(stored))
+            if max_age
+                .and_then(|max_age| stored.rotation_due(max_age))
+                .is_none_or(|due| due > SystemTime::now()) =>
+        {
+            println!("[WEBXASH] Loaded DTLS certificate from {CERTIFICATE_PATH}");
+            return Ok(stored);
+        }
+        Ok(_) => {}
+        Err(e) => eprintln!("[WEBXASH] Ignoring DTLS certificate {CERTIFICATE_PATH}: {e}"),
+    }
+
+    generate().await
+}
+
+/// Generate a new certificate and store it.
+///
+/// A certificate that cannot be stored is still used for this run.
+pub async fn generate() -> Result<StoredCertificate, BoxError> {
+    let stored = StoredCertificate {
+        certificate: RTCCertificate::from_key_pair(rcgen::KeyPair::generate()?)?,
+        created: SystemTime::now(),
+    };
+    match save(&stored.certificate).await {
+        Ok(()) => println!("[WEBXASH] Generated DTLS certificate in {CERTIFICATE_PATH}"),
+        Err(e) => eprintln!("[WEBXASH] Failed to store DTLS certificate: {e}"),
+    }
+    Ok(stored)
+}
+
+/// Read the stored certificate; `None` if there is none.
+///
+/// The file's modification time is taken as its creation time.
+async fn load() -> Result<Option<StoredCertificate>, BoxError> {
+    let pem = match tokio::fs::read_to_string(CERTIFICATE_PATH).await {
+        Ok(pem) => pem,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e.into()),
+    };
+    let created = tokio::fs::metadata(CERTIFICATE_PATH).await?.modified()?;
+
+    Ok(Some(StoredCertificate {
+        certificate: RTCCertificate::from_pem(&pem)?,
+        created,
+    }))
+}
+
+/// Write the certificate and its private key, readable by the owner only.
+async fn save(certificate: &RTCCertificate) -> std::io::Result<()> {
+    let path = Path::new(CERTIFICATE_PATH);
+    if let Some(dir) = path.parent() {
+        tokio::fs::create_dir_all(dir).await?;
+    }
+
+    let mut options = tokio::fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    options.mode(0o600);
+    let mut file = options.open(path).await?;
+    tokio::io::AsyncWriteExt::write_all(&mut file, certificate.serialize_pem().as_bytes()).await
+}
@@ -0,0 +1,100 @@
+//! ICE servers shared by the server peer and the browser.
+//!
+//! TURN servers get either the static credentials or, with a shared secret,
+//! time-limited ones following the TURN REST API convention used by coturn:
+//! the username is `<expiry unix time>:<user>` and the credential is the
+//! base64 HMAC-SHA1 of the username.
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use base64::Engine;
+use hmac::{Hmac, Mac};
+use serde::Serialize;
+use sha1::Sha1;
+use webrtc::ice_transport::ice_server::RTCIceServer;
+
+use crate::config::{is_turn_url, PluginConfig};
+
+/// An ICE server, serialized like the browser's `RTCIceServer` dictionary
+#[derive(Debug, Clone, Serialize)]
+pub struct IceServer {
+    pub urls: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub username: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub credential: Option<String>,
+}
+
+impl IceServer {
+    /// Convert to the webrtc-rs configuration type.
+    pub fn to_rtc(&self) -> RTCIceServer {
+        RTCIceServer {
+            urls: self.urls.clone(),
+            username: self.username.clone().unwrap_or_default(),
+            credential: self.credential.clone().unwrap_or_default(),
+            ..Default::default()
+        }
+    }
+}
+
+/// ICE servers for one signaling session.
+///
+/// `user` names the session in TURN REST usernames. TURN servers are left out
+/// when no credentials are configured.
+pub fn ice_servers(config: &PluginConfig, user: &str) -> Vec<IceServer> {
+    let (turn_urls, stun_urls): (Vec<String>, Vec<String>) = config
+        .ice_servers
+        .iter()
+        .cloned()
+        .partition(|url| is_turn_url(url));
+
+    let mut servers = Vec::new();
+    if !stun_urls.is_empty() {
+        servers.push(IceServer {
+            urls: stun_urls,
+            username: None,
+            credential: None,
+        });
+    }
+
+    let credentials = match (
+        &config.turn_secret,
+        &config.turn_username,
+        &config.turn_credential,
+    ) {
+        (Some(secret), _, _) => SystemTime::now()
+            .checked_add(config.turn_credential_ttl)
+            .and_then(|expiry| rest_credentials(secret, user, expiry)),
+        (None, Some(username), Some(credential)) => Some((username.clone(), credential.clone())),
+        _ => None,
+    };
+    if let Some((username, credential)) = credentials.filter(|_| !turn_urls.is_empty()) {
+        servers.push(IceServer {
+            urls: turn_urls,
+            username: Some(username),
+            credential: Some(credential),
+        });
+    }
+
+    servers
+}
+
+/// TURN REST username and credential valid until `expiry`.
+pub fn rest_credentials(secret: &str, user: &str, expiry: SystemTime) -> Option<(String, String)> {
+    let expiry = expiry
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default();
+    // The first `:` separates the expiry, so the user part must not contain one
+    let username = format!("{expiry}:{}", user.replace(':', "."));
+    let credential = rest_password(secret, &username)?;
+
+    Some((username, credential))
+}
+
+/// TURN REST credential of `username`: base64 HMAC-SHA1 with the shared secret.
+pub fn rest_password(secret: &str, username: &str) -> Option<String> {
+    let mut mac = Hmac::<Sha1>::new_from_slice(secret.as_bytes()).ok()?;
+    mac.update(username.as_bytes());
+    Some(base64::engine::general_purpose::STANDARD.encode(mac.finalize().into_bytes()))
+}
@@ -1,5 +1,11 @@
 //! WebRTC peer connection management.
 
+mod certificate;
+mod ice;
 mod signaling;
+mod stats;
 
-pub use signaling::create_peer_and_channels;
+pub use certificate::{fingerprint as dtls_fingerprint, Fingerprint};
+pub use ice::{ice_servers, rest_credentials, rest_password, IceServer};
+pub use signaling::{create_channels, ChannelLayout, PeerFactory, GAME_CHANNEL_ID};
+pub use stats::{all_peer_stats, peer_stats, PeerStats, StatsCollector};
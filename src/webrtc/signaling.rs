@@ -3,85 +3,224 @@
 //! Adapted from the webxash3d-proxy signaling module.
 
 use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+
+use parking_lot::RwLock;
 
-use webrtc::api::interceptor_registry::register_default_interceptors;
-use webrtc::api::media_engine::MediaEngine;
 use webrtc::api::setting_engine::SettingEngine;
-use webrtc::api::APIBuilder;
+use webrtc::api::{APIBuilder, API};
 use webrtc::data_channel::data_channel_init::RTCDataChannelInit;
 use webrtc::data_channel::RTCDataChannel;
+use webrtc::ice::network_type::NetworkType;
+use webrtc::ice::udp_mux::{UDPMuxDefault, UDPMuxParams};
+use webrtc::ice::udp_network::{EphemeralUDP, UDPNetwork};
 use webrtc::ice_transport::ice_server::RTCIceServer;
-use webrtc::interceptor::registry::Registry;
 use webrtc::peer_connection::configuration::RTCConfiguration;
 use webrtc::peer_connection::RTCPeerConnection;
 
-/// Create a new WebRTC peer connection with data channels.
+use crate::config::{ChannelReliability, IcePorts, PluginConfig};
+use crate::webrtc::certificate::{self, StoredCertificate};
+
+/// Retransmissions of unreliable channels.
 ///
-/// Returns the peer connection and the write/read data channels.
-pub async fn create_peer_and_channels(
-    public_ip: Option<String>,
-) -> Result<
-    (
-        Arc<RTCPeerConnection>,
-        Arc<RTCDataChannel>,
-        Arc<RTCDataChannel>,
-    ),
-    Box<dyn std::error::Error + Send + Sync>,
-> {
-    let peer = create_peer_connection(public_ip).await?;
-    let peer = Arc::new(peer);
-
-    let dc_options = RTCDataChannelInit {
-        ordered: Some(true),
-        ..Default::default()
-    };
+/// webrtc-rs reads a limit of 0 as "no limit" (fully reliable), so a single
+/// retransmission is the closest it gets to none. Channels are unordered, so
+/// a retransmission never holds back later packets.
+const UNRELIABLE_MAX_RETRANSMITS: u16 = 1;
 
-    // Create "write" channel - for sending data TO the browser
-    let write_channel = peer
-        .create_data_channel("write", Some(dc_options.clone()))
-        .await?;
+/// Delay before retrying a failed certificate rotation
+const ROTATION_RETRY: Duration = Duration::from_hours(1);
 
-    // Create "read" channel - for receiving data FROM the browser
-    let read_channel = peer.create_data_channel("read", Some(dc_options)).await?;
+/// Stream id of the pre-negotiated game channel
+pub const GAME_CHANNEL_ID: u16 = 0;
+
+/// Data channels carrying the game traffic of a peer connection
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ChannelLayout {
+    /// Separate in-band "write" and "read" channels, as in the original proxy
+    #[default]
+    Pair,
+    /// One bidirectional channel, pre-negotiated with id [`GAME_CHANNEL_ID`]
+    Single,
+}
+
+impl ChannelLayout {
+    /// Parse a layout name from the client `hello`.
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "pair" => Some(Self::Pair),
+            "single" => Some(Self::Single),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Pair => "pair",
+            Self::Single => "single",
+        }
+    }
+}
 
-    Ok((peer, write_channel, read_channel))
+/// Builds the peer connections of one runtime.
+///
+/// The API (data channels only: no media codecs or interceptors), the ICE
+/// network and the DTLS certificate are set up once and shared by all peers.
+pub struct PeerFactory {
+    api: API,
+    /// Certificate of new peer connections; existing ones keep theirs
+    certificate: RwLock<StoredCertificate>,
+    /// Age at which the certificate is replaced
+    rotation: Option<Duration>,
 }
 
-/// Create a new WebRTC peer connection.
-async fn create_peer_connection(
-    public_ip: Option<String>,
-) -> Result<RTCPeerConnection, Box<dyn std::error::Error + Send + Sync>> {
-    let mut media_engine = MediaEngine::default();
-    media_engine.register_default_codecs()?;
+impl PeerFactory {
+    /// Set up the API for `config` and load the stored certificate.
+    pub async fn new(
+        config: &PluginConfig,
+    ) -> Result<Self, Box<dyn std::error::Error + Send + Sync>> {
+        let mut setting_engine = SettingEngine::default();
+
+        // Set public IP for NAT traversal if provided
+        if let Some(ip) = config.public_ip.clone() {
+            setting_engine.set_nat_1to1_ips(
+                vec![ip],
+                webrtc::ice_transport::ice_candidate_type::RTCIceCandidateType::Host,
+            );
+        }
 
-    let mut registry = Registry::new();
-    registry = register_default_interceptors(registry, &mut media_engine)?;
+        match config.ice_ports {
+            IcePorts::Any => {}
+            IcePorts::Single(port) => {
+                // Connections are told apart by their ICE username fragment
+                let socket = tokio::net::UdpSocket::bind(("0.0.0.0", port)).await?;
+                println!("[WEBXASH] ICE listening on udp/{port}");
+                let mux = UDPMuxDefault::new(UDPMuxParams::new(socket));
+                setting_engine.set_udp_network(UDPNetwork::Muxed(mux));
+                // The shared socket is IPv4 only
+                setting_engine.set_network_types(vec![NetworkType::Udp4]);
+            }
+            IcePorts::Range(min, max) => {
+                setting_engine.set_udp_network(UDPNetwork::Ephemeral(EphemeralUDP::new(min, max)?));
+            }
+        }
 
-    let mut setting_engine = SettingEngine::default();
+        let api = APIBuilder::new()
+            .with_setting_engine(setting_engine)
+            .build();
 
-    // Set public IP for NAT traversal if provided
-    if let Some(ip) = public_ip {
-        setting_engine.set_nat_1to1_ips(
-            vec![ip],
-            webrtc::ice_transport::ice_candidate_type::RTCIceCandidateType::Host,
-        );
+        let stored = certificate::load_or_generate(config.dtls_rotation).await?;
+        stored.publish();
+
+        Ok(Self {
+            api,
+            certificate: RwLock::new(stored),
+            rotation: config.dtls_rotation,
+        })
     }
 
-    let api = APIBuilder::new()
-        .with_media_engine(media_engine)
-        .with_interceptor_registry(registry)
-        .with_setting_engine(setting_engine)
-        .build();
+    /// Replace the certificate whenever it reaches the rotation age.
+    pub async fn rotate_certificate(&self) {
+        let Some(rotation) = self.rotation else {
+            return;
+        };
+
+        let mut due = self.certificate.read().rotation_due(rotation);
+        while let Some(at) = due {
+            let wait = at.duration_since(SystemTime::now()).unwrap_or_default();
+            tokio::time::sleep(wait).await;
 
-    let config = RTCConfiguration {
-        ice_servers: vec![RTCIceServer {
-            urls: vec!["stun:stun.l.google.com:19302".to_string()],
+            match certificate::generate().await {
+                Ok(stored) => {
+                    due = stored.rotation_due(rotation);
+                    // Announce it only once new peers use it
+                    let mut current = self.certificate.write();
+                    *current = stored;
+                    current.publish();
+                    drop(current);
+                    println!("[WEBXASH] Rotated the DTLS certificate");
+                }
+                Err(e) => {
+                    eprintln!("[WEBXASH] Failed to rotate the DTLS certificate: {e}");
+                    due = SystemTime::now().checked_add(ROTATION_RETRY);
+                }
+            }
+        }
+    }
+
+    /// Create a new WebRTC peer connection with data channels.
+    ///
+    /// `ice_servers` should match the list sent to the browser.
+    /// Returns the peer connection and the write/read data channels.
+    pub async fn create_peer_and_channels(
+        &self,
+        ice_servers: Vec<RTCIceServer>,
+        reliability: ChannelReliability,
+    ) -> Result<
+        (
+            Arc<RTCPeerConnection>,
+            Arc<RTCDataChannel>,
+            Arc<RTCDataChannel>,
+        ),
+        Box<dyn std::error::Error + Send + Sync>,
+    > {
+        let config = RTCConfiguration {
+            ice_servers,
+            certificates: vec![self.certificate.read().certificate.clone()],
+            ..Default::default()
+        };
+        let peer = Arc::new(self.api.new_peer_connection(config).await?);
+
+        let (write_channel, read_channel) =
+            create_channels(&peer, reliability, ChannelLayout::Pair).await?;
+
+        Ok((peer, write_channel, read_channel))
+    }
+}
+
+/// Create the write/read data channels on a peer connection.
+///
+/// With [`ChannelLayout::Single`] both are the same pre-negotiated channel.
+/// Channels created before the SCTP association is up replace closed ones
+/// without renegotiation.
+pub async fn create_channels(
+    peer: &RTCPeerConnection,
+    reliability: ChannelReliability,
+    layout: ChannelLayout,
+) -> Result<(Arc<RTCDataChannel>, Arc<RTCDataChannel>), Box<dyn std::error::Error + Send + Sync>> {
+    let dc_options = match reliability {
+        ChannelReliability::Unreliable => RTCDataChannelInit {
+            ordered: Some(false),
+            max_retransmits: Some(UNRELIABLE_MAX_RETRANSMITS),
+            ..Default::default()
+        },
+        ChannelReliability::Reliable => RTCDataChannelInit {
+            ordered: Some(true),
             ..Default::default()
-        }],
-        ..Default::default()
+        },
     };
 
-    let peer = api.new_peer_connection(config).await?;
+    if layout == ChannelLayout::Single {
+        // Opens with the association: no DCEP handshake, no channel to wait for
+        let game_channel = peer
+            .create_data_channel(
+                "game",
+                Some(RTCDataChannelInit {
+                    negotiated: Some(GAME_CHANNEL_ID),
+                    ..dc_options
+                }),
+            )
+            .await?;
+        return Ok((game_channel.clone(), game_channel));
+    }
+
+    // Create "write" channel - for sending data TO the browser
+    let write_channel = peer
+        .create_data_channel("write", Some(dc_options.clone()))
+        .await?;
+
+    // Create "read" channel - for receiving data FROM the browser
+    let read_channel = peer.create_data_channel("read", Some(dc_options)).await?;
 
-    Ok(peer)
+    Ok((write_channel, read_channel))
 }
@@ -0,0 +1,147 @@
+//! Periodic collection of per-peer WebRTC statistics.
+//!
+//! A collector task polls `RTCPeerConnection::get_stats` for each signaling
+//! session and publishes a summary keyed by client id.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Weak};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use once_cell::sync::Lazy;
+use parking_lot::Mutex;
+use serde::Serialize;
+use tokio::task::JoinHandle;
+use webrtc::ice::candidate::CandidatePairState;
+use webrtc::peer_connection::RTCPeerConnection;
+use webrtc::stats::StatsReportType;
+
+/// Interval between stats collections
+const COLLECT_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Latest stats of each peer, keyed by client id
+static PEER_STATS: Lazy<Mutex<HashMap<String, PeerStats>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// Summary of a peer connection's transport
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct PeerStats {
+    /// Unix time (ms) of the collection
+    pub updated_at: u64,
+    /// Local candidate type of the selected pair (`host`, `srflx`, `prflx`, `relay`)
+    pub local_candidate_type: Option<String>,
+    /// Remote candidate type of the selected pair
+    pub remote_candidate_type: Option<String>,
+    /// Remote address of the selected pair
+    pub remote_address: Option<String>,
+    /// Bytes sent on the ICE transport
+    pub bytes_sent: u64,
+    /// Bytes received on the ICE transport
+    pub bytes_received: u64,
+    /// Data channel messages sent
+    pub messages_sent: u64,
+    /// Data channel messages received
+    pub messages_received: u64,
+}
+
+/// Get the latest stats of all peers.
+pub fn all_peer_stats() -> HashMap<String, PeerStats> {
+    PEER_STATS.lock().clone()
+}
+
+/// Get the latest stats of one peer.
+pub fn peer_stats(client_id: &str) -> Option<PeerStats> {
+    PEER_STATS.lock().get(client_id).cloned()
+}
+
+/// Collects stats for one peer until dropped.
+pub struct StatsCollector {
+    client_id: String,
+    task: JoinHandle<()>,
+}
+
+impl StatsCollector {
+    /// Start collecting stats for `peer` under `client_id`.
+    pub fn start(client_id: String, peer: &Arc<RTCPeerConnection>) -> Self {
+        let peer = Arc::downgrade(peer);
+        let task = tokio::spawn({
+            let client_id = client_id.clone();
+            async move { collect_loop(client_id, peer).await }
+        });
+
+        Self { client_id, task }
+    }
+}
+
+impl Drop for StatsCollector {
+    fn drop(&mut self) {
+        self.task.abort();
+        PEER_STATS.lock().remove(&self.client_id);
+    }
+}
+
+async fn collect_loop(client_id: String, peer: Weak<RTCPeerConnection>) {
+    let mut interval = tokio::time::interval(COLLECT_INTERVAL);
+    loop {
+        interval.tick().await;
+
+        // Stop once the signaling session released the peer
+        let Some(peer) = peer.upgrade() else {
+            break;
+        };
+        let stats = collect(&peer).await;
+        PEER_STATS.lock().insert(client_id.clone(), stats);
+    }
+}
+
+/// Summarize a stats report.
+async fn collect(peer: &RTCPeerConnection) -> PeerStats {
+    let report = peer.get_stats().await;
+
+    let mut stats = PeerStats {
+        updated_at: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| u64::try_from(d.as_millis()).unwrap_or(u64::MAX)),
+        ..PeerStats::default()
+    };
+
+    // The nominated, succeeded pair is the one carrying traffic
+    let selected = report.reports.values().find_map(|r| match r {
+        StatsReportType::CandidatePair(pair)
+            if pair.nominated && pair.state == CandidatePairState::Succeeded =>
+        {
+            Some(pair)
+        }
+        _ => None,
+    });
+
+    if let Some(pair) = selected {
+        for r in report.reports.values() {
+            match r {
+                StatsReportType::LocalCandidate(c) if c.id == pair.local_candidate_id => {
+                    stats.local_candidate_type = Some(c.candidate_type.to_string());
+                }
+                StatsReportType::RemoteCandidate(c) if c.id == pair.remote_candidate_id => {
+                    stats.remote_candidate_type = Some(c.candidate_type.to_string());
+                    stats.remote_address = Some(format!("{}:{}", c.ip, c.port));
+                }
+                _ => {}
+            }
+        }
+    }
+
+    for r in report.reports.values() {
+        match r {
+            StatsReportType::SCTPTransport(t) | StatsReportType::Transport(t) => {
+                stats.bytes_sent = stats.bytes_sent.max(t.bytes_sent as u64);
+                stats.bytes_received = stats.bytes_received.max(t.bytes_received as u64);
+            }
+            StatsReportType::DataChannel(dc) => {
+                stats.messages_sent += dc.messages_sent as u64;
+                stats.messages_received += dc.messages_received as u64;
+            }
+            _ => {}
+        }
+    }
+
+    stats
+}